@@ -1,7 +1,127 @@
-pub use typesafe_builder_derive::Builder;
+pub use typesafe_builder_derive::{Builder, builder};
 
 /// Empty marker for typesafe builder
+#[deprecated(
+    since = "2.0.0",
+    note = "generated builders track each field with the states of their own state module, e.g. `user_builder::name::Unset`"
+)]
 pub struct _TypesafeBuilderEmpty;
 
 /// Filled marker for typesafe builder
+#[deprecated(
+    since = "2.0.0",
+    note = "generated builders track each field with the states of their own state module, e.g. `user_builder::name::Set`"
+)]
 pub struct _TypesafeBuilderFilled;
+
+/// Items referenced by the code generated by `#[derive(Builder)]`.
+#[doc(hidden)]
+pub mod __private {
+    pub use core::convert::{From, Into};
+    pub use core::default::Default;
+    pub use core::error::Error;
+    pub use core::fmt;
+    pub use core::marker::{PhantomData, Send, Sync};
+    pub use core::option::Option::{self, None, Some};
+    pub use core::result::Result::{self, Err, Ok};
+    pub use std::boxed::Box;
+
+    /// Evaluates conditions and counts fields at the type level, so that the
+    /// bounds checking them grow with their size rather than with the number
+    /// of combinations of the states they refer to.
+    pub mod logic {
+        use core::marker::PhantomData;
+
+        pub struct True;
+
+        pub struct False;
+
+        /// `True` or `False`.
+        pub trait Bool {
+            type Not: Bool;
+            type And<R: Bool>: Bool;
+            type Or<R: Bool>: Bool;
+            type Xor<R: Bool>: Bool;
+            /// `N`, plus one if `Self` is `True`.
+            type Add<N: Count>: Count;
+            /// `T`, plus one if `Self` is `True`.
+            type Tally<T: Tally>: Tally;
+        }
+
+        impl Bool for True {
+            type Not = False;
+            type And<R: Bool> = R;
+            type Or<R: Bool> = True;
+            type Xor<R: Bool> = R::Not;
+            type Add<N: Count> = Succ<N>;
+            type Tally<T: Tally> = T::Next;
+        }
+
+        impl Bool for False {
+            type Not = True;
+            type And<R: Bool> = False;
+            type Or<R: Bool> = R;
+            type Xor<R: Bool> = R;
+            type Add<N: Count> = N;
+            type Tally<T: Tally> = T;
+        }
+
+        pub struct Zero;
+
+        pub struct Succ<N>(PhantomData<N>);
+
+        /// `Zero` or a `Succ` of a count.
+        pub trait Count {
+            type IsZero: Bool;
+            /// `Self` minus one, or `Zero`.
+            type Pred: Count;
+            type Eq<R: Count>: Bool;
+            type Lt<R: Count>: Bool;
+        }
+
+        impl Count for Zero {
+            type IsZero = True;
+            type Pred = Zero;
+            type Eq<R: Count> = R::IsZero;
+            type Lt<R: Count> = <R::IsZero as Bool>::Not;
+        }
+
+        impl<N: Count> Count for Succ<N> {
+            type IsZero = False;
+            type Pred = N;
+            type Eq<R: Count> = <<R::IsZero as Bool>::Not as Bool>::And<N::Eq<R::Pred>>;
+            type Lt<R: Count> = <<R::IsZero as Bool>::Not as Bool>::And<N::Lt<R::Pred>>;
+        }
+
+        pub struct One;
+
+        pub struct Many;
+
+        /// `Zero`, `One` or `Many`, the number of fields of a group set.
+        pub trait Tally {
+            type Next: Tally;
+        }
+
+        impl Tally for Zero {
+            type Next = One;
+        }
+
+        impl Tally for One {
+            type Next = Many;
+        }
+
+        impl Tally for Many {
+            type Next = Many;
+        }
+
+        /// Whether a field in this state is set, or enabled for a flag.
+        pub trait IsSet {
+            type Output: Bool;
+        }
+
+        /// Whether a selector in this state has chosen `Variant`.
+        pub trait IsChosen<Variant> {
+            type Output: Bool;
+        }
+    }
+}
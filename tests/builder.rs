@@ -1,4 +1,4 @@
-use typesafe_builder::*;
+use typesafe_builder::Builder;
 
 #[derive(Builder)]
 struct User {
@@ -1,58 +1,419 @@
+mod check_constraints;
+mod collect_condition_fields;
+mod default_order;
 mod eval_condition;
+mod generate_build_error;
 mod generate_build_methods;
+mod generate_enum_builders;
+mod generate_fn_builder;
 mod generate_setter_methods;
+mod generate_state_module;
+mod group_constraints;
+mod named_conditions;
+mod state_constraint;
 mod validate_condition;
 
 use crate::{
     Input,
-    input::{DefaultValue, InputField, Requirement},
+    input::{
+        DefaultRequirement, DefaultSource, DefaultValue, Group, InputField, NamedCondition,
+        Requirement,
+    },
 };
+use check_constraints::check_constraints;
+use collect_condition_fields::collect_condition_fields;
+use darling::ast::Data;
+use default_order::evaluation_order;
 use eval_condition::eval_condition;
+use generate_build_error::{build_error_name, build_error_variant, generate_build_error};
 use generate_build_methods::generate_build_methods;
+use generate_enum_builders::generate_enum_builders;
+pub use generate_fn_builder::{generate_fn_builder, generate_impl_builders};
 use generate_setter_methods::generate_setter_methods;
+use generate_state_module::generate_state_module;
+use group_constraints::validate_groups;
+use named_conditions::NamedConditions;
 use proc_macro2::TokenStream as TokenStream2;
-use quote::quote;
-use syn::{Ident, PathArguments, Type};
+use quote::{ToTokens, quote, quote_spanned};
+use syn::{
+    Expr, GenericParam, Generics, Ident, Index, ItemFn, Member, Path, PathArguments, ReturnType,
+    Signature, Type, spanned::Spanned,
+};
 use validate_condition::validate_condition_fields;
 
-type FieldInfo = (Ident, Type, Requirement, Option<DefaultValue>, bool);
+struct FieldInfo {
+    /// Name of the field inside the builder, its state module and conditions:
+    /// the field name, or `_0`, `_1`, ... for tuple fields.
+    ident: Ident,
+    /// How the field is addressed when constructing the target.
+    member: Member,
+    /// Name of the setter, `with_<field>` unless overridden.
+    setter: Ident,
+    ty: Type,
+    requirement: Requirement,
+    default: Option<DefaultValue>,
+    into: bool,
+    /// Condition under which the field must not be set.
+    conflicts_with: Option<Expr>,
+    /// Validator run on the value of the field by `try_build`.
+    validate: Option<Path>,
+}
+
+impl FieldInfo {
+    /// Name of the field as written by the user, e.g. `name` or `0`.
+    fn display_name(&self) -> String {
+        member_name(&self.member)
+    }
+
+    /// Names of the states of the field in its state module. The field starts
+    /// in the first one, which conditions consider not set, and is moved to
+    /// the others by its setters: `Unset` and `Set`, `Disabled` and `Enabled`
+    /// for a flag, or `Unset` followed by the variants of a selector.
+    fn states(&self) -> Vec<Ident> {
+        let names = match &self.requirement {
+            Requirement::Flag => vec!["Disabled".to_string(), "Enabled".to_string()],
+            Requirement::Selector(variants) => std::iter::once("Unset".to_string())
+                .chain(variants.iter().map(|variant| variant.to_string()))
+                .collect(),
+            _ => vec!["Unset".to_string(), "Set".to_string()],
+        };
+        names
+            .iter()
+            .map(|name| Ident::new(name, proc_macro2::Span::call_site()))
+            .collect()
+    }
+
+    /// Whether `build` needs a variant of a selector to have been chosen,
+    /// which is the case unless its type is `Option<T>`.
+    fn is_required_selector(&self) -> bool {
+        matches!(self.requirement, Requirement::Selector(_)) && !is_type_option(&self.ty)
+    }
+
+    /// Setters choosing each variant of a selector, e.g.
+    /// `with_transport_tcp` for the variant `Tcp`.
+    fn selector_setters(&self) -> Vec<(&Ident, Ident)> {
+        match &self.requirement {
+            Requirement::Selector(variants) => variants
+                .iter()
+                .map(|variant| {
+                    let name = format!("{}_{}", self.setter, to_snake_case(variant));
+                    (variant, Ident::new(&name, variant.span()))
+                })
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Calls of the setters as shown in diagnostics, e.g. `` `.with_name(..)` ``
+    /// or `` `.enable_use_auth()` ``.
+    fn setter_call(&self) -> String {
+        match self.requirement {
+            Requirement::Flag => format!("`.{}()`", self.setter),
+            Requirement::Selector(_) => self
+                .selector_setters()
+                .iter()
+                .map(|(_, setter)| format!("`.{setter}()`"))
+                .collect::<Vec<_>>()
+                .join(" or "),
+            _ => format!("`.{}(..)`", self.setter),
+        }
+    }
+
+    /// Setter turning a flag off, e.g. `disable_use_auth` next to the
+    /// `enable_use_auth` setter.
+    fn disable_setter(&self) -> Ident {
+        let enable = self.setter.to_string();
+        let name = enable.strip_prefix("enable_").unwrap_or(&enable);
+        Ident::new(&format!("disable_{name}"), self.setter.span())
+    }
+}
+
+fn member_name(member: &Member) -> String {
+    match member {
+        Member::Named(ident) => ident.to_string(),
+        Member::Unnamed(index) => index.index.to_string(),
+    }
+}
+
+/// What a generated builder produces: the struct itself, one variant of the
+/// enum, or the result of a function called with the collected arguments.
+struct BuildTarget {
+    /// The struct or enum the builder produces, or for a function the struct
+    /// its arguments are read as.
+    ident: Ident,
+    /// Name used in diagnostics, e.g. `User` or `Command::Create`.
+    display_name: String,
+    /// Path of the constructed value, e.g. `User` or `Command::Create`.
+    constructor: TokenStream2,
+    /// Groups declared on the struct or variant.
+    groups: Vec<Group>,
+    /// Named conditions declared on the struct or variant.
+    conditions: Vec<NamedCondition>,
+    /// Source of the values of unannotated fields, declared on the struct.
+    default_source: Option<DefaultSource>,
+    /// Requirement of unannotated fields, declared on the struct or enum.
+    default_requirement: Option<DefaultRequirement>,
+    /// Function whose arguments the fields are, called by `call` instead of
+    /// constructing the target.
+    call: Option<FunctionCall>,
+}
+
+/// A function a builder collects the arguments of.
+struct FunctionCall {
+    /// Signature of the function, with `Self` in its return type replaced by
+    /// the type of its `impl` block.
+    sig: Signature,
+    /// Path the function is called by, e.g. `connect` or `<Pool>::new`.
+    path: TokenStream2,
+    /// A free function, declared inside the finisher.
+    item: Option<ItemFn>,
+    /// Type of the receiver of a method, held by the builder and passed as
+    /// the first argument.
+    receiver: Option<Type>,
+}
+
+impl BuildTarget {
+    /// Name of the method finishing the builder, `build`, or `call` for a
+    /// function.
+    fn finisher(&self) -> &'static str {
+        match self.call {
+            Some(_) => "call",
+            None => "build",
+        }
+    }
+
+    /// What the finisher does, as shown in diagnostics, e.g. `` build `User` ``
+    /// or `` call `connect` ``.
+    fn action(&self) -> String {
+        format!("{} `{}`", self.finisher(), self.display_name)
+    }
+
+    /// Type returned by the finisher.
+    fn output(&self, generics: &Generics) -> TokenStream2 {
+        match &self.call {
+            Some(function) => match &function.sig.output {
+                ReturnType::Default => quote! { () },
+                ReturnType::Type(_, ty) => quote! { #ty },
+            },
+            None => {
+                let ident = &self.ident;
+                let (_, ty_generics, _) = generics.split_for_impl();
+                quote! { #ident #ty_generics }
+            }
+        }
+    }
+
+    /// Type the builder is marked with, using every generic parameter even
+    /// when no field does: the target, or the parameters of the function.
+    fn marker(&self, generics: &Generics) -> TokenStream2 {
+        if self.call.is_none() {
+            return self.output(generics);
+        }
+        let params = generics.params.iter().filter_map(|param| match param {
+            GenericParam::Lifetime(param) => {
+                let lifetime = &param.lifetime;
+                Some(quote! { &#lifetime () })
+            }
+            GenericParam::Type(param) => {
+                let ident = &param.ident;
+                Some(quote! { #ident })
+            }
+            GenericParam::Const(_) => None,
+        });
+        quote! { ( #( #params, )* ) }
+    }
+
+    /// Produces the target from the values of the fields, given in order.
+    fn finish(&self, members: &[&Member], values: &[impl ToTokens]) -> TokenStream2 {
+        let Some(function) = &self.call else {
+            let constructor = &self.constructor;
+            return quote! {
+                #constructor {
+                    #( #members : #values, )*
+                }
+            };
+        };
+        // Type arguments that only appear in the return type cannot be
+        // inferred, so every one is given.
+        let args = function
+            .sig
+            .generics
+            .params
+            .iter()
+            .filter_map(|param| match param {
+                GenericParam::Type(param) => Some(&param.ident),
+                GenericParam::Const(param) => Some(&param.ident),
+                GenericParam::Lifetime(_) => None,
+            })
+            .collect::<Vec<_>>();
+        let path = &function.path;
+        let turbofish = (!args.is_empty()).then(|| quote! { ::< #( #args ),* > });
+        let receiver = function
+            .receiver
+            .as_ref()
+            .map(|_| quote! { self._typesafe_builder_target.0, });
+        let call = quote! { #path #turbofish ( #receiver #( #values ),* ) };
+        let call = match function.sig.unsafety {
+            Some(_) => quote! { unsafe { #call } },
+            None => call,
+        };
+        let awaited = function.sig.asyncness.map(|_| quote! { .await });
+        let item = &function.item;
+        // Callers are warned when they start the builder of a deprecated
+        // function, not where the builder calls it.
+        let output = Ident::new("output", proc_macro2::Span::mixed_site());
+        quote! {
+            {
+                #item
+                #[allow(deprecated)]
+                let #output = #call #awaited;
+                #output
+            }
+        }
+    }
+}
 
 pub fn derive_builder_impl(input: Input) -> Result<TokenStream2, darling::Error> {
     let name = input.ident();
-    let builder_name = Ident::new(&input.builder_name(), name.span());
+
+    match input.data() {
+        Data::Struct(fields) => {
+            let builder_name = Ident::new(&input.builder_name(), name.span());
+            let target = BuildTarget {
+                ident: name.clone(),
+                display_name: name.to_string(),
+                constructor: quote! { #name },
+                groups: input.groups().to_vec(),
+                conditions: input.conditions().cloned().collect(),
+                default_source: input.default_source(),
+                default_requirement: input.default_requirement(),
+                call: None,
+            };
+            let (builder, _) = generate_builder(&input, &fields.fields, &builder_name, &target)?;
+            Ok(builder)
+        }
+        Data::Enum(variants) => {
+            if let Some(group) = input.groups().first() {
+                return Err(darling::Error::custom(
+                    "Groups of an enum must be declared on its variants",
+                )
+                .with_span(&group.name));
+            }
+            if let Some(condition) = input.conditions().next() {
+                return Err(darling::Error::custom(
+                    "Conditions of an enum must be declared on its variants",
+                )
+                .with_span(&condition.name));
+            }
+            generate_enum_builders(&input, &variants)
+        }
+    }
+}
+
+/// Generates a builder for one set of named fields, returning the builder
+/// items together with the type of a freshly created builder.
+fn generate_builder(
+    input: &Input,
+    fields: &[&InputField],
+    builder_name: &Ident,
+    target: &BuildTarget,
+) -> Result<(TokenStream2, TokenStream2), darling::Error> {
+    let name = input.ident();
     let generics = input.generics();
 
-    let field_infos = extract_field_infos(&input)?;
-    let n_fields = field_infos.len();
-    let type_params = generate_type_params(n_fields, name.span());
-    let default_generics = (0..n_fields)
-        .map(|_| quote! { _TypesafeBuilderEmpty })
+    let crate_path = input.crate_path();
+    let private = quote! { #crate_path::__private };
+
+    let field_infos = extract_field_infos(fields, target)?;
+    validate_groups(&target.groups, &field_infos)?;
+    check_constraints(&field_infos, target)?;
+    evaluation_order(&field_infos)?;
+    let type_params = generate_type_params(&field_infos, &target.groups, name.span());
+    let declared_type_params = type_params.iter().flatten().collect::<Vec<_>>();
+    let state_module = Ident::new(&to_snake_case(builder_name), builder_name.span());
+    let default_generics = field_infos
+        .iter()
+        .zip(type_params.iter())
+        .filter(|(_, tp)| tp.is_some())
+        .map(|(field, _)| {
+            let ident = &field.ident;
+            let initial = &field.states()[0];
+            quote! { #state_module::#ident::#initial }
+        })
         .collect::<Vec<_>>();
 
     let (_, _, where_clause) = generics.split_for_impl();
     let generic_params = &generics.params;
 
-    let builder_fields = generate_builder_fields(&field_infos, &type_params);
-    let builder_initialization = generate_builder_initialization(&field_infos);
-    let setter_methods = generate_setter_methods(
+    let builder_fields = generate_builder_fields(&field_infos, &type_params, &private);
+    let builder_initialization =
+        generate_builder_initialization(&field_infos, &type_params, &private);
+    let (setter_methods, settable_impls) = generate_setter_methods(
         &field_infos,
         &type_params,
-        &builder_name,
+        &private,
+        &state_module,
+        builder_name,
         generics,
-        name.span(),
     );
-    let build_impls = generate_build_methods(&field_infos, &builder_name, name, generics);
+    let build_impls = generate_build_methods(
+        &field_infos,
+        &type_params,
+        &private,
+        &state_module,
+        builder_name,
+        target,
+        input,
+    );
+    let build_error = generate_build_error(&field_infos, &private, builder_name, target);
+    let selector_checks = field_infos.iter().filter_map(selector_check);
+    let state_module_def = generate_state_module(
+        &field_infos,
+        &type_params,
+        &state_module,
+        builder_name,
+        target,
+    );
+
+    // Variants of a generic enum need not use every parameter of the enum.
+    let marker = target.marker(generics);
+    // The builder of a method also holds its receiver.
+    let receiver = target
+        .call
+        .as_ref()
+        .and_then(|function| function.receiver.as_ref());
+    let (target_marker, new_params, new_target) = match receiver {
+        Some(receiver_ty) => {
+            let receiver = Ident::new("receiver", proc_macro2::Span::mixed_site());
+            (
+                quote! {
+                    _typesafe_builder_target : (#receiver_ty, #private::PhantomData<fn() -> #marker>),
+                },
+                quote! { #receiver: #receiver_ty },
+                quote! { (#receiver, #private::PhantomData) },
+            )
+        }
+        None => (
+            quote! {
+                _typesafe_builder_target : #private::PhantomData<fn() -> #marker>,
+            },
+            quote! {},
+            quote! { #private::PhantomData },
+        ),
+    };
 
     let builder_struct = if generic_params.is_empty() {
         quote! {
-            pub struct #builder_name < #( #type_params ),* > {
+            pub struct #builder_name < #( #declared_type_params ),* > {
                 #( #builder_fields )*
+                #target_marker
             }
         }
     } else {
         quote! {
-            pub struct #builder_name < #generic_params, #( #type_params ),* > {
+            pub struct #builder_name < #generic_params, #( #declared_type_params ),* > #where_clause {
                 #( #builder_fields )*
+                #target_marker
             }
         }
     };
@@ -61,8 +422,11 @@ pub fn derive_builder_impl(input: Input) -> Result<TokenStream2, darling::Error>
         quote! {
             impl #builder_name < #( #default_generics ),* > {
                 #[inline]
-                pub fn new() -> Self {
-                    Self { #( #builder_initialization )* }
+                pub fn new(#new_params) -> Self {
+                    Self {
+                        #( #builder_initialization )*
+                        _typesafe_builder_target : #new_target,
+                    }
                  }
             }
         }
@@ -70,8 +434,11 @@ pub fn derive_builder_impl(input: Input) -> Result<TokenStream2, darling::Error>
         quote! {
             impl < #generic_params > #builder_name < #generic_params, #( #default_generics ),* > #where_clause {
                 #[inline]
-                pub fn new() -> Self {
-                    Self { #( #builder_initialization )* }
+                pub fn new(#new_params) -> Self {
+                    Self {
+                        #( #builder_initialization )*
+                        _typesafe_builder_target : #new_target,
+                    }
                  }
             }
         }
@@ -79,135 +446,312 @@ pub fn derive_builder_impl(input: Input) -> Result<TokenStream2, darling::Error>
 
     let setter_impl = if generic_params.is_empty() {
         quote! {
-            impl < #( #type_params ),* > #builder_name < #( #type_params ),* > {
+            impl < #( #declared_type_params ),* > #builder_name < #( #declared_type_params ),* > {
                 #( #setter_methods )*
             }
         }
     } else {
         quote! {
-            impl < #generic_params, #( #type_params ),* > #builder_name < #generic_params, #( #type_params ),* > #where_clause {
+            impl < #generic_params, #( #declared_type_params ),* > #builder_name < #generic_params, #( #declared_type_params ),* > #where_clause {
                 #( #setter_methods )*
             }
         }
     };
 
-    Ok(quote! {
-        #builder_struct
+    let initial_builder_ty = if generic_params.is_empty() {
+        quote! { #builder_name < #( #default_generics ),* > }
+    } else {
+        quote! { #builder_name < #generic_params, #( #default_generics ),* > }
+    };
+
+    Ok((
+        quote! {
+            #state_module_def
 
-        #new_impl
+            #builder_struct
 
-        #setter_impl
+            #new_impl
 
-        #( #build_impls )*
-    })
+            #setter_impl
+
+            #( #settable_impls )*
+
+            #( #build_impls )*
+
+            #build_error
+
+            #( #selector_checks )*
+        },
+        initial_builder_ty,
+    ))
 }
 
-fn extract_field_infos(builder_input: &Input) -> Result<Vec<FieldInfo>, darling::Error> {
+fn extract_field_infos(
+    fields: &[&InputField],
+    target: &BuildTarget,
+) -> Result<Vec<FieldInfo>, darling::Error> {
     let mut field_infos = Vec::new();
-    let mut all_field_names = Vec::new();
-
-    for field in builder_input
-        .data()
-        .take_struct()
-        .expect("only named structs are supported")
-        .fields
+    let all_field_names = fields
         .iter()
-    {
-        let ident = field
-            .ident()
-            .cloned()
-            .expect("darling guarantees named fields");
-        all_field_names.push(ident.to_string());
-    }
-
-    for field in builder_input
-        .data()
-        .take_struct()
-        .expect("only named structs are supported")
-        .fields
+        .enumerate()
+        .map(|(index, field)| field_name(index, field).to_string())
+        .collect::<Vec<_>>();
+    // Conditions refer to fields by name, and compare selectors with their
+    // variants, e.g. `transport == Tcp`, which is looked up as `transport::Tcp`.
+    let condition_names = fields
         .iter()
-    {
-        let ident = field
-            .ident()
-            .cloned()
-            .expect("darling guarantees named fields");
+        .enumerate()
+        .flat_map(|(index, field)| {
+            let name = field_name(index, field);
+            field
+                .selector_variants()
+                .iter()
+                .map(move |variant| format!("{name}::{variant}"))
+        })
+        .chain(all_field_names.iter().cloned())
+        .collect::<Vec<_>>();
+    let named_conditions = NamedConditions::resolve(&target.conditions, &condition_names)?;
+    let noun = match target.call {
+        Some(_) => "Argument",
+        None => "Field",
+    };
 
-        let req = field
-            .requirement()
-            .map_err(|err| darling::Error::custom(format!("Invalid requirement: {err}")))?;
+    for (index, field) in fields.iter().enumerate() {
+        let ident = field_name(index, field);
 
-        if let Requirement::Conditional(expr) = &req
-            && let Err(err) = validate_condition_fields(expr, &all_field_names)
+        // Unannotated fields take their value from the struct-level
+        // `default_from` when left unset, or the requirement given with
+        // `fields(default_requirement = "...")`.
+        let unannotated_requirement = target
+            .default_requirement
+            .filter(|_| field.is_unannotated())
+            .map(|default_requirement| match default_requirement {
+                DefaultRequirement::Required => Requirement::Always,
+                DefaultRequirement::Optional => Requirement::Optional,
+                DefaultRequirement::Infer if is_type_option(field.ty()) => Requirement::Optional,
+                DefaultRequirement::Infer => Requirement::Always,
+            });
+        let (mut req, default) = if target.default_source.is_some() && field.is_unannotated() {
+            (Requirement::Default, Some(DefaultValue::Struct))
+        } else if let Some(req) = unannotated_requirement {
+            (req, None)
+        } else {
+            let req = field
+                .requirement()
+                .map_err(|err| spanned_error("Invalid requirement", err))?;
+            (req, field.default())
+        };
+
+        if let Requirement::Conditional(expr)
+        | Requirement::OptionalIf(expr)
+        | Requirement::ForbiddenIf(expr) = &mut req
         {
-            return Err(darling::Error::custom(err));
+            *expr = named_conditions.expand(expr);
+            validate_condition_fields(expr, &condition_names)?;
         }
 
-        if let Requirement::OptionalIf(expr) = &req
-            && let Err(err) = validate_condition_fields(expr, &all_field_names)
-        {
-            return Err(darling::Error::custom(err));
+        let conflicts_with = field
+            .conflicts_with()
+            .map_err(|err| spanned_error("Invalid conflicts_with", err))?
+            .map(|expr| named_conditions.expand(&expr));
+        if let Some(expr) = &conflicts_with {
+            validate_condition_fields(expr, &condition_names)?;
         }
 
         let requirement_is_option_based = match &req {
-            Requirement::Optional | Requirement::Conditional(_) | Requirement::OptionalIf(_) => {
-                true
-            }
-            Requirement::Always | Requirement::Default => false,
+            Requirement::Optional
+            | Requirement::Conditional(_)
+            | Requirement::OptionalIf(_)
+            | Requirement::ForbiddenIf(_) => true,
+            Requirement::Always
+            | Requirement::Default
+            | Requirement::Flag
+            | Requirement::Selector(_) => false,
         };
 
-        if requirement_is_option_based && !is_type_option(field.ty()) {
+        let member = match field.ident() {
+            Some(field_ident) => Member::Named(field_ident.clone()),
+            None => Member::Unnamed(Index {
+                index: index as u32,
+                span: field.ty().span(),
+            }),
+        };
+
+        // A field with a default holds a value even when left unset.
+        if requirement_is_option_based && default.is_none() && !is_type_option(field.ty()) {
             let requirement_name = match &req {
                 Requirement::Optional => "optional",
                 Requirement::Conditional(_) => "required_if",
                 Requirement::OptionalIf(_) => "optional_if",
+                Requirement::ForbiddenIf(_) => "forbidden_if",
                 _ => unreachable!(),
             };
+            let display_name = member_name(&member);
+            let message = if field.is_unannotated() {
+                format!(
+                    "{noun} `{display_name}` is optional through `fields(default_requirement = \"optional\")` and must be of type `Option<T>`, or have its own requirement"
+                )
+            } else {
+                format!(
+                    "{noun} `{display_name}` marked with `#[builder({requirement_name})]` must be of type `Option<T>`"
+                )
+            };
+            return Err(darling::Error::custom(message).with_span(&field.ty()));
+        }
+        if matches!(req, Requirement::Flag) && !is_type_flag(field.ty()) {
             return Err(darling::Error::custom(format!(
-                "Field `{ident}` marked with `#[builder({requirement_name})]` must be of type `Option<T>`"
+                "{noun} `{}` marked with `#[builder(flag)]` must be of type `bool` or `Option<bool>`",
+                member_name(&member)
             ))
             .with_span(&field.ty()));
         }
+        if let Requirement::Selector(variants) = &req {
+            validate_selector(&member_name(&member), field.ty(), variants)?;
+        }
+
+        // Flags are set through `enable_<field>` and `disable_<field>`, with
+        // `#[builder(setter(name = "..."))]` replacing the field name.
+        let setter = match (&req, field.setter_name()) {
+            (Requirement::Flag, Some(name)) => Ident::new(&format!("enable_{name}"), name.span()),
+            (_, Some(setter)) => setter.clone(),
+            (req, None) => {
+                let prefix = match req {
+                    Requirement::Flag => "enable",
+                    _ => "with",
+                };
+                match &member {
+                    Member::Named(field_ident) => {
+                        Ident::new(&format!("{prefix}_{field_ident}"), field_ident.span())
+                    }
+                    Member::Unnamed(index) => {
+                        Ident::new(&format!("{prefix}_{}", index.index), index.span)
+                    }
+                }
+            }
+        };
 
-        field_infos.push((
+        field_infos.push(FieldInfo {
             ident,
-            field.ty().clone(),
-            req,
-            field.default().cloned(),
-            InputField::into(field),
-        ));
+            member,
+            setter,
+            ty: field.ty().clone(),
+            requirement: req,
+            default,
+            into: InputField::into(field),
+            conflicts_with,
+            validate: field.validate().cloned(),
+        });
     }
 
     Ok(field_infos)
 }
 
-fn generate_type_params(n_fields: usize, span: proc_macro2::Span) -> Vec<Ident> {
-    (0..n_fields)
-        .map(|i| Ident::new(&format!("_TypesafeBuilder{i}"), span))
+/// Prefixes the message of `err`, keeping its span.
+fn spanned_error(prefix: &str, err: syn::Error) -> darling::Error {
+    syn::Error::new(err.span(), format!("{prefix}: {err}")).into()
+}
+
+/// Named fields keep their name, tuple fields are called `_0`, `_1`, ...
+fn field_name(index: usize, field: &InputField) -> Ident {
+    match field.ident() {
+        Some(ident) => ident.clone(),
+        None => Ident::new(&format!("_{index}"), field.ty().span()),
+    }
+}
+
+/// Only fields that can affect whether `build` is available get a typestate
+/// parameter. Optional and default fields without conflicts that no condition
+/// or group refers to are stored as plain `Option<T>`.
+fn generate_type_params(
+    field_infos: &[FieldInfo],
+    groups: &[Group],
+    span: proc_macro2::Span,
+) -> Vec<Option<Ident>> {
+    let mut condition_fields = groups
+        .iter()
+        .flat_map(|group| group.fields.iter().map(|field| field.to_string()))
+        .collect::<Vec<_>>();
+    for field in field_infos {
+        if let Requirement::Conditional(expr)
+        | Requirement::OptionalIf(expr)
+        | Requirement::ForbiddenIf(expr) = &field.requirement
+        {
+            collect_condition_fields(expr, &mut condition_fields);
+        }
+        if let Some(expr) = &field.conflicts_with {
+            collect_condition_fields(expr, &mut condition_fields);
+        }
+    }
+
+    field_infos
+        .iter()
+        .map(|field| {
+            let ident = &field.ident;
+            let tracked = match &field.requirement {
+                Requirement::Always
+                | Requirement::Conditional(_)
+                | Requirement::OptionalIf(_)
+                | Requirement::ForbiddenIf(_)
+                | Requirement::Flag
+                | Requirement::Selector(_) => true,
+                Requirement::Optional | Requirement::Default => {
+                    field.conflicts_with.is_some() || condition_fields.contains(&ident.to_string())
+                }
+            };
+            // Reserved names, so that the parameters cannot shadow a type of
+            // the fields such as `AppState`.
+            tracked.then(|| {
+                Ident::new(
+                    &format!("__TypesafeBuilderState{}", to_pascal_case(ident)),
+                    span,
+                )
+            })
+        })
         .collect()
 }
 
 fn generate_builder_fields<'a>(
     field_infos: &'a [FieldInfo],
-    type_params: &'a [Ident],
+    type_params: &'a [Option<Ident>],
+    private: &'a TokenStream2,
 ) -> impl Iterator<Item = proc_macro2::TokenStream> + 'a {
     field_infos
         .iter()
         .zip(type_params.iter())
-        .map(|((ident, ty, req, _, _), tp)| {
+        .map(move |(field, tp)| {
+            let (ident, ty) = (&field.ident, &field.ty);
             let phantom = Ident::new(&format!("_{ident}"), ident.span());
-            match req {
-                &Requirement::Always | &Requirement::Default => {
+            let phantom_field = tp.as_ref().map(|tp| {
+                quote! { #phantom : #private::PhantomData<#tp>, }
+            });
+            match &field.requirement {
+                Requirement::Always | Requirement::Default => {
+                    quote! {
+                        #ident : #private::Option<#ty>,
+                        #phantom_field
+                    }
+                }
+                _ if field.default.is_some() => {
                     quote! {
-                        #ident : Option<#ty>,
-                        #phantom : std::marker::PhantomData<#tp>,
+                        #ident : #private::Option<#ty>,
+                        #phantom_field
                     }
                 }
-                &Requirement::Optional
-                | &Requirement::Conditional(_)
-                | &Requirement::OptionalIf(_) => {
+                Requirement::Selector(_) if field.is_required_selector() => {
+                    quote! {
+                        #ident : #private::Option<#ty>,
+                        #phantom_field
+                    }
+                }
+                Requirement::Optional
+                | Requirement::Conditional(_)
+                | Requirement::OptionalIf(_)
+                | Requirement::ForbiddenIf(_)
+                | Requirement::Flag
+                | Requirement::Selector(_) => {
                     quote! {
                         #ident : #ty,
-                        #phantom : std::marker::PhantomData<#tp>,
+                        #phantom_field
                     }
                 }
             }
@@ -216,41 +760,35 @@ fn generate_builder_fields<'a>(
 
 fn generate_builder_initialization<'a>(
     field_infos: &'a [FieldInfo],
+    type_params: &'a [Option<Ident>],
+    private: &'a TokenStream2,
 ) -> impl Iterator<Item = proc_macro2::TokenStream> + 'a {
-    field_infos.iter().map(|(ident, _ty, req, default, _)| {
-        let phantom = Ident::new(&format!("_{ident}"), ident.span());
-        match req {
-            Requirement::Default => {
-                if let Some(default_val) = default {
-                    match default_val {
-                        DefaultValue::Bare => {
-                            quote! {
-                                #ident : Some(Default::default()),
-                                #phantom : std::marker::PhantomData,
-                            }
-                        }
-                        DefaultValue::Expression(expr) => {
-                            quote! {
-                                #ident : Some(#expr),
-                                #phantom : std::marker::PhantomData,
-                            }
-                        }
-                    }
-                } else {
+    field_infos
+        .iter()
+        .zip(type_params.iter())
+        .map(move |(field, tp)| {
+            let ident = &field.ident;
+            let phantom = Ident::new(&format!("_{ident}"), ident.span());
+            let phantom_init = tp
+                .as_ref()
+                .map(|_| quote! { #phantom : #private::PhantomData, });
+            // Defaults are computed by `build`, and only for fields left
+            // unset.
+            match &field.requirement {
+                Requirement::Flag => {
                     quote! {
-                        #ident : None,
-                        #phantom : std::marker::PhantomData,
+                        #ident : #private::Default::default(),
+                        #phantom_init
                     }
                 }
-            }
-            _ => {
-                quote! {
-                    #ident : None,
-                    #phantom : std::marker::PhantomData,
+                _ => {
+                    quote! {
+                        #ident : #private::None,
+                        #phantom_init
+                    }
                 }
             }
-        }
-    })
+        })
 }
 
 fn is_type_option(field_ty: &Type) -> bool {
@@ -264,9 +802,102 @@ fn is_type_option(field_ty: &Type) -> bool {
     false
 }
 
-fn extract_arg_type(field_ty: &Type, req: &Requirement) -> proc_macro2::TokenStream {
-    match req {
-        Requirement::Optional | Requirement::Conditional(_) | Requirement::OptionalIf(_) => {
+/// Checks that a selector holds a path to an enum, optionally wrapped in
+/// `Option<T>`, and lists each of its variants once.
+fn validate_selector(name: &str, ty: &Type, variants: &[Ident]) -> Result<(), darling::Error> {
+    if selector_enum(ty).is_none() {
+        return Err(darling::Error::custom(format!(
+            "Field `{name}` marked with `#[builder(selector(...))]` must be of a fieldless enum type, or `Option` of one"
+        ))
+        .with_span(ty));
+    }
+    for (idx, variant) in variants.iter().enumerate() {
+        if variants[..idx].contains(variant) {
+            return Err(darling::Error::custom(format!(
+                "Variant `{variant}` appears more than once in the selector `{name}`"
+            ))
+            .with_span(variant));
+        }
+        if ["Unset", "Requirement", "Conflict", "Settable"].contains(&variant.to_string().as_str())
+        {
+            return Err(darling::Error::custom(format!(
+                "Variant `{variant}` of the selector `{name}` clashes with a name of the generated state module"
+            ))
+            .with_span(variant));
+        }
+    }
+    Ok(())
+}
+
+/// Matches on the enum of a selector with one arm per listed variant, so that
+/// the compiler rejects lists missing a variant or naming one the enum lacks.
+fn selector_check(field: &FieldInfo) -> Option<TokenStream2> {
+    let Requirement::Selector(variants) = &field.requirement else {
+        return None;
+    };
+    let enum_path = selector_enum(&field.ty)?;
+    let value = Ident::new("value", enum_path.span());
+    Some(quote_spanned! {enum_path.span()=>
+        const _: fn(#enum_path) = |#value| match #value {
+            #( #enum_path::#variants => {} )*
+        };
+    })
+}
+
+/// Path of the enum held by a selector of type `E` or `Option<E>`.
+fn selector_enum(ty: &Type) -> Option<&Path> {
+    let ty = match ty {
+        Type::Path(type_path) if type_path.qself.is_none() && is_type_option(ty) => {
+            match &type_path.path.segments.last()?.arguments {
+                PathArguments::AngleBracketed(params) => match params.args.first()? {
+                    syn::GenericArgument::Type(inner) => inner,
+                    _ => return None,
+                },
+                _ => return None,
+            }
+        }
+        _ => ty,
+    };
+    match ty {
+        Type::Path(type_path)
+            if type_path.qself.is_none()
+                && type_path
+                    .path
+                    .segments
+                    .last()
+                    .is_some_and(|segment| segment.arguments.is_empty()) =>
+        {
+            Some(&type_path.path)
+        }
+        _ => None,
+    }
+}
+
+/// Whether the type of a flag is `bool` or `Option<bool>`.
+fn is_type_flag(field_ty: &Type) -> bool {
+    let is_bool =
+        |ty: &Type| matches!(ty, Type::Path(type_path) if type_path.path.is_ident("bool"));
+    if let Type::Path(type_path) = field_ty
+        && let Some(last_segment) = type_path.path.segments.last()
+        && last_segment.ident == "Option"
+        && let PathArguments::AngleBracketed(params) = &last_segment.arguments
+        && let Some(syn::GenericArgument::Type(inner)) = params.args.first()
+    {
+        return is_bool(inner);
+    }
+    is_bool(field_ty)
+}
+
+fn extract_arg_type(field: &FieldInfo) -> proc_macro2::TokenStream {
+    let field_ty = &field.ty;
+    if field.default.is_some() {
+        return quote! { #field_ty };
+    }
+    match &field.requirement {
+        Requirement::Optional
+        | Requirement::Conditional(_)
+        | Requirement::OptionalIf(_)
+        | Requirement::ForbiddenIf(_) => {
             if let Type::Path(path) = field_ty
                 && let Some(seg) = path.path.segments.last()
                 && seg.ident == "Option"
@@ -277,6 +908,51 @@ fn extract_arg_type(field_ty: &Type, req: &Requirement) -> proc_macro2::TokenStr
             }
             quote! { #field_ty }
         }
-        Requirement::Always | Requirement::Default => quote! { #field_ty },
+        Requirement::Always
+        | Requirement::Default
+        | Requirement::Flag
+        | Requirement::Selector(_) => quote! { #field_ty },
+    }
+}
+
+fn to_pascal_case(ident: &Ident) -> String {
+    let pascal = ident
+        .to_string()
+        .split('_')
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect::<String>(),
+                None => String::new(),
+            }
+        })
+        .collect::<String>();
+
+    // Tuple fields such as `_0` would otherwise start with a digit.
+    if pascal.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("Field{pascal}")
+    } else {
+        pascal
+    }
+}
+
+fn to_snake_case(ident: &Ident) -> String {
+    let chars = ident.to_string().chars().collect::<Vec<_>>();
+    let mut snake = String::new();
+    for (i, &c) in chars.iter().enumerate() {
+        if c.is_uppercase() {
+            let after_lower = i > 0 && !chars[i - 1].is_uppercase() && chars[i - 1] != '_';
+            let ends_acronym = i > 0
+                && chars[i - 1].is_uppercase()
+                && chars.get(i + 1).is_some_and(|next| next.is_lowercase());
+            if after_lower || ends_acronym {
+                snake.push('_');
+            }
+            snake.extend(c.to_lowercase());
+        } else {
+            snake.push(c);
+        }
     }
+    snake
 }
@@ -0,0 +1,288 @@
+use super::{
+    BuildTarget, FieldInfo, collect_condition_fields, eval_condition,
+    generate_state_module::render_condition,
+    group_constraints::group_members,
+    state_constraint::{Constraint, build_constraints, condition_vars, state_combinations},
+    validate_condition::variant_comparison,
+};
+use crate::input::{Group, Requirement};
+use std::collections::HashMap;
+use syn::{Expr, ExprBinary, ExprCall, ExprParen, ExprPath, ExprUnary, Ident};
+
+/// Fields whose combinations are enumerated at most, beyond which a check is
+/// skipped rather than slowing down compilation.
+const MAX_ENUMERATED_FIELDS: usize = 16;
+
+/// Rejects constraints that cannot be what the user meant: conditions
+/// referring to the field they constrain, conditions that hold for every or
+/// no combination of set fields, and constraint sets no combination of set
+/// fields satisfies, which would leave the finisher uncallable.
+pub fn check_constraints(
+    field_infos: &[FieldInfo],
+    target: &BuildTarget,
+) -> Result<(), darling::Error> {
+    let groups = target.groups.as_slice();
+    let mut errors = darling::Error::accumulator();
+
+    for field in field_infos {
+        for (attribute, expr) in conditions(field) {
+            if let Some(reference) = find_reference(expr, &field.ident) {
+                errors.push(
+                    darling::Error::custom(format!(
+                        "the `{attribute}` condition of `{}` refers to the field itself",
+                        field.display_name()
+                    ))
+                    .with_span(reference),
+                );
+            } else if let Some(always) = constant_value(field_infos, expr) {
+                errors.push(
+                    darling::Error::custom(constant_condition_message(
+                        field_infos,
+                        field,
+                        attribute,
+                        expr,
+                        always,
+                    ))
+                    .with_span(expr),
+                );
+            }
+        }
+    }
+    errors.finish()?;
+
+    // Contradictions are only looked for once every condition makes sense.
+    let mut errors = darling::Error::accumulator();
+    for component in components(field_infos, groups) {
+        if component.len() > MAX_ENUMERATED_FIELDS {
+            continue;
+        }
+        let satisfiable = state_combinations(field_infos, &component).any(|states| {
+            let vars = condition_vars(field_infos, &component, &states);
+            satisfies(field_infos, groups, &component, &vars)
+        });
+        if !satisfiable {
+            let names = component
+                .iter()
+                .map(|&i| format!("`{}`", field_infos[i].display_name()))
+                .collect::<Vec<_>>()
+                .join(", ");
+            errors.push(
+                darling::Error::custom(format!(
+                    "the constraints on {names} contradict each other: no combination of set fields satisfies them, so `{}` could never be {}",
+                    target.display_name,
+                    match target.call {
+                        Some(_) => "called",
+                        None => "built",
+                    }
+                ))
+                .with_span(&field_infos[component[0]].ident),
+            );
+        }
+    }
+    errors.finish()
+}
+
+/// Conditions of `field` with the attribute each was given with.
+fn conditions(field: &FieldInfo) -> Vec<(&'static str, &Expr)> {
+    let requirement = match &field.requirement {
+        Requirement::Conditional(expr) => Some(("required_if", expr)),
+        Requirement::OptionalIf(expr) => Some(("optional_if", expr)),
+        Requirement::ForbiddenIf(expr) => Some(("forbidden_if", expr)),
+        Requirement::Always
+        | Requirement::Optional
+        | Requirement::Default
+        | Requirement::Flag
+        | Requirement::Selector(_) => None,
+    };
+    let conflict = field
+        .conflicts_with
+        .as_ref()
+        .map(|expr| ("conflicts_with", expr));
+    requirement.into_iter().chain(conflict).collect()
+}
+
+/// The first reference to the field `ident` in `expr`.
+fn find_reference<'a>(expr: &'a Expr, ident: &Ident) -> Option<&'a Ident> {
+    match expr {
+        Expr::Path(ExprPath { path, .. }) => path.get_ident().filter(|found| *found == ident),
+        Expr::Paren(ExprParen { expr, .. }) | Expr::Unary(ExprUnary { expr, .. }) => {
+            find_reference(expr, ident)
+        }
+        Expr::Binary(binary) if variant_comparison(binary).is_some() => {
+            find_reference(&binary.left, ident)
+        }
+        Expr::Binary(ExprBinary { left, right, .. }) => {
+            find_reference(left, ident).or_else(|| find_reference(right, ident))
+        }
+        Expr::Call(ExprCall { args, .. }) => args.iter().find_map(|arg| find_reference(arg, ident)),
+        _ => None,
+    }
+}
+
+/// Whether `expr` holds for every (`Some(true)`) or no (`Some(false)`)
+/// combination of states of the fields it refers to, given that required
+/// fields are always set.
+fn constant_value(field_infos: &[FieldInfo], expr: &Expr) -> Option<bool> {
+    let members = referenced_fields(expr)
+        .iter()
+        .filter_map(|name| field_infos.iter().position(|field| field.ident == name))
+        .collect::<Vec<_>>();
+    if members.len() > MAX_ENUMERATED_FIELDS {
+        return None;
+    }
+    let mut values = state_combinations(field_infos, &members)
+        .filter(|states| {
+            members
+                .iter()
+                .zip(states)
+                .all(|(&i, &state)| state != 0 || !is_required(&field_infos[i]))
+        })
+        .map(|states| eval_condition(expr, &condition_vars(field_infos, &members, &states)));
+    let first = values.next()?;
+    values.all(|value| value == first).then_some(first)
+}
+
+/// Whether `field` is always set when `build` is called.
+fn is_required(field: &FieldInfo) -> bool {
+    matches!(field.requirement, Requirement::Always) || field.is_required_selector()
+}
+
+fn constant_condition_message(
+    field_infos: &[FieldInfo],
+    field: &FieldInfo,
+    attribute: &str,
+    expr: &Expr,
+    always: bool,
+) -> String {
+    let name = field.display_name();
+    let condition = render_condition(expr);
+    let verdict = if always {
+        "always holds"
+    } else {
+        "never holds"
+    };
+    let required = required_fields(field_infos, expr);
+    let reason = if required.is_empty() {
+        String::new()
+    } else {
+        let names = required
+            .iter()
+            .map(|name| format!("`{name}`"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let verb = if required.len() == 1 { "is" } else { "are" };
+        format!(" because {names} {verb} required")
+    };
+    let consequence = match (attribute, always) {
+        ("required_if", true) | ("optional_if", false) => {
+            format!("`{name}` is always required, use `#[builder(required)]` instead")
+        }
+        ("required_if", false) | ("optional_if", true) => {
+            let replacement = if field.default.is_some() {
+                "default"
+            } else {
+                "optional"
+            };
+            format!("`{name}` is never required, use `#[builder({replacement})]` instead")
+        }
+        (_, true) => format!("`{name}` can never be set"),
+        (_, false) => format!("`{name}` is never restricted, remove `{attribute}`"),
+    };
+    format!(
+        "the `{attribute}` condition `{condition}` of `{name}` {verdict}{reason}, so {consequence}"
+    )
+}
+
+/// Names of the required fields `expr` refers to, which are always set when
+/// `build` is called.
+fn required_fields(field_infos: &[FieldInfo], expr: &Expr) -> Vec<String> {
+    referenced_fields(expr)
+        .into_iter()
+        .filter(|name| {
+            field_infos
+                .iter()
+                .any(|field| field.ident == name && is_required(field))
+        })
+        .collect()
+}
+
+fn referenced_fields(expr: &Expr) -> Vec<String> {
+    let mut fields = Vec::new();
+    collect_condition_fields(expr, &mut fields);
+    fields
+}
+
+/// Partitions the constrained fields into sets of fields that constrain each
+/// other, directly or through other fields.
+fn components(field_infos: &[FieldInfo], groups: &[Group]) -> Vec<Vec<usize>> {
+    let position = |name: &String| field_infos.iter().position(|field| field.ident == name);
+    let mut links = Vec::new();
+    for (idx, field) in field_infos.iter().enumerate() {
+        for (_, (condition, _)) in build_constraints(field) {
+            links.push(vec![idx]);
+            if let Some(expr) = condition {
+                links
+                    .last_mut()
+                    .unwrap()
+                    .extend(referenced_fields(expr).iter().filter_map(position));
+            }
+        }
+    }
+    links.extend(groups.iter().map(|group| group_members(group, field_infos)));
+
+    let mut component_of = (0..field_infos.len()).collect::<Vec<_>>();
+    let mut constrained = vec![false; field_infos.len()];
+    for link in &links {
+        for &i in link {
+            constrained[i] = true;
+            let (from, to) = (component_of[i], component_of[link[0]]);
+            component_of
+                .iter_mut()
+                .filter(|component| **component == from)
+                .for_each(|component| *component = to);
+        }
+    }
+
+    let mut components = Vec::<Vec<usize>>::new();
+    for idx in (0..field_infos.len()).filter(|&i| constrained[i]) {
+        match components
+            .iter_mut()
+            .find(|component| component_of[component[0]] == component_of[idx])
+        {
+            Some(component) => component.push(idx),
+            None => components.push(vec![idx]),
+        }
+    }
+    components
+}
+
+/// Whether setting exactly the fields marked in `filled` satisfies every
+/// constraint on the fields of `component`.
+fn satisfies(
+    field_infos: &[FieldInfo],
+    groups: &[Group],
+    component: &[usize],
+    filled: &HashMap<String, bool>,
+) -> bool {
+    let is_filled = |field: &FieldInfo| filled[&field.ident.to_string()];
+    let fields_satisfied = component.iter().all(|&i| {
+        let field = &field_infos[i];
+        build_constraints(field)
+            .into_iter()
+            .all(|(_, (condition, allowed)): (_, Constraint)| {
+                let holds = condition.is_none_or(|expr| eval_condition(expr, filled));
+                allowed(is_filled(field), holds)
+            })
+    });
+    let groups_satisfied = groups.iter().all(|group| {
+        let members = group_members(group, field_infos);
+        !members.iter().any(|i| component.contains(i))
+            || group.kind.allows(
+                members
+                    .iter()
+                    .filter(|&&i| is_filled(&field_infos[i]))
+                    .count() as u32,
+            )
+    });
+    fields_satisfied && groups_satisfied
+}
@@ -0,0 +1,29 @@
+use super::validate_condition::variant_comparison;
+use syn::{Expr, ExprBinary, ExprCall, ExprPath, ExprUnary};
+
+pub fn collect_condition_fields(expr: &Expr, fields: &mut Vec<String>) {
+    match expr {
+        Expr::Path(ExprPath { path, .. }) => {
+            let key = path.segments.last().unwrap().ident.to_string();
+            if !fields.contains(&key) {
+                fields.push(key);
+            }
+        }
+        Expr::Paren(expr_paren) => collect_condition_fields(&expr_paren.expr, fields),
+        Expr::Unary(ExprUnary { expr, .. }) => collect_condition_fields(expr, fields),
+        // The variant a selector is compared with is not a field.
+        Expr::Binary(binary) if variant_comparison(binary).is_some() => {
+            collect_condition_fields(&binary.left, fields);
+        }
+        Expr::Binary(ExprBinary { left, right, .. }) => {
+            collect_condition_fields(left, fields);
+            collect_condition_fields(right, fields);
+        }
+        Expr::Call(ExprCall { args, .. }) => {
+            for arg in args {
+                collect_condition_fields(arg, fields);
+            }
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,196 @@
+use super::FieldInfo;
+use crate::input::DefaultValue;
+use proc_macro2::{Delimiter, TokenStream, TokenTree};
+use quote::ToTokens;
+
+/// Indices of the fields the default expression of `field` refers to by
+/// name, e.g. `name` in `default = "name.clone()"`.
+pub fn default_dependencies(field: &FieldInfo, field_infos: &[FieldInfo]) -> Vec<usize> {
+    let Some(DefaultValue::Expression(expr)) = &field.default else {
+        return Vec::new();
+    };
+    let mut names = Vec::new();
+    collect_names(expr.to_token_stream(), &[], &mut names);
+    field_infos
+        .iter()
+        .enumerate()
+        .filter(|(_, other)| names.iter().any(|name| other.ident == name))
+        .map(|(idx, _)| idx)
+        .collect()
+}
+
+/// Macros whose first string literal is a format string, which may capture
+/// variables with `{name}`.
+const FORMAT_MACROS: &[&str] = &[
+    "format",
+    "format_args",
+    "print",
+    "println",
+    "eprint",
+    "eprintln",
+    "write",
+    "writeln",
+    "panic",
+    "assert",
+    "assert_eq",
+    "assert_ne",
+    "debug_assert",
+    "debug_assert_eq",
+    "debug_assert_ne",
+    "unreachable",
+    "todo",
+    "unimplemented",
+];
+
+/// Identifiers of `tokens` that may name a local variable: not a method or
+/// field accessed with `.`, a segment of a longer path, a macro, a called
+/// function, a struct literal key or a parameter of an enclosing closure.
+/// Variables captured by format strings count as well.
+fn collect_names(tokens: TokenStream, bound: &[String], names: &mut Vec<String>) {
+    let tokens = tokens.into_iter().collect::<Vec<_>>();
+    let is_punct = |idx: Option<usize>, ch: char| match idx.and_then(|idx| tokens.get(idx)) {
+        Some(TokenTree::Punct(punct)) => punct.as_char() == ch,
+        _ => false,
+    };
+    let mut bound = bound.to_vec();
+    let mut idx = 0;
+    while idx < tokens.len() {
+        match &tokens[idx] {
+            // A `|` where an operand is expected opens the parameters of a
+            // closure, which shadow fields for the rest of the expression.
+            TokenTree::Punct(punct)
+                if punct.as_char() == '|'
+                    && match idx.checked_sub(1).map(|prev| &tokens[prev]) {
+                        None | Some(TokenTree::Punct(_)) => true,
+                        Some(TokenTree::Ident(ident)) => ident == "move",
+                        Some(_) => false,
+                    } =>
+            {
+                let end = tokens[idx + 1..]
+                    .iter()
+                    .position(
+                        |token| matches!(token, TokenTree::Punct(punct) if punct.as_char() == '|'),
+                    )
+                    .map_or(tokens.len(), |offset| idx + 1 + offset);
+                collect_bindings(tokens[idx + 1..end].iter().cloned().collect(), &mut bound);
+                idx = end;
+            }
+            TokenTree::Ident(ident) => {
+                let name = ident.to_string();
+                if is_punct(Some(idx + 1), '!')
+                    && FORMAT_MACROS.contains(&name.as_str())
+                    && let Some(TokenTree::Group(group)) = tokens.get(idx + 2)
+                {
+                    collect_captures(group.stream(), &bound, names);
+                }
+                let accessed = is_punct(idx.checked_sub(1), '.')
+                    || (is_punct(idx.checked_sub(1), ':') && is_punct(idx.checked_sub(2), ':'));
+                let qualified = (is_punct(Some(idx + 1), ':') && is_punct(Some(idx + 2), ':'))
+                    || is_punct(Some(idx + 1), '!')
+                    || matches!(
+                        tokens.get(idx + 1),
+                        Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Parenthesis
+                    );
+                let key = is_punct(Some(idx + 1), ':') && !is_punct(Some(idx + 2), ':');
+                if !accessed && !qualified && !key && !bound.contains(&name) {
+                    names.push(name);
+                }
+            }
+            TokenTree::Group(group) => collect_names(group.stream(), &bound, names),
+            _ => {}
+        }
+        idx += 1;
+    }
+}
+
+/// Identifiers the closure parameters `tokens` bind.
+fn collect_bindings(tokens: TokenStream, bound: &mut Vec<String>) {
+    for token in tokens {
+        match token {
+            TokenTree::Ident(ident) => bound.push(ident.to_string()),
+            TokenTree::Group(group) => collect_bindings(group.stream(), bound),
+            _ => {}
+        }
+    }
+}
+
+/// Variables the format string of the macro arguments `tokens` captures,
+/// e.g. `name` in `"{name}!"` or `"{name:?}"`.
+fn collect_captures(tokens: TokenStream, bound: &[String], names: &mut Vec<String>) {
+    let Some(format) = tokens.into_iter().find_map(|token| match token {
+        TokenTree::Literal(literal) => syn::parse2::<syn::LitStr>(literal.into_token_stream()).ok(),
+        _ => None,
+    }) else {
+        return;
+    };
+    let format = format.value();
+    let mut chars = format.chars().peekable();
+    while let Some(ch) = chars.next() {
+        if ch != '{' {
+            continue;
+        }
+        if chars.next_if_eq(&'{').is_some() {
+            continue;
+        }
+        let mut name = String::new();
+        while let Some(ch) = chars.next_if(|ch| *ch != '}' && *ch != ':') {
+            name.push(ch);
+        }
+        let name = name.trim();
+        if syn::parse_str::<syn::Ident>(name).is_ok() && !bound.iter().any(|bound| bound == name) {
+            names.push(name.to_string());
+        }
+    }
+}
+
+/// Order in which `build` computes the values of the fields, placing every
+/// field after the fields its default refers to and otherwise keeping the
+/// order of declaration. Fails when defaults refer to each other in a cycle.
+pub fn evaluation_order(field_infos: &[FieldInfo]) -> Result<Vec<usize>, darling::Error> {
+    let dependencies = field_infos
+        .iter()
+        .map(|field| default_dependencies(field, field_infos))
+        .collect::<Vec<_>>();
+    let mut order = Vec::new();
+    let mut visiting = Vec::new();
+    for idx in 0..field_infos.len() {
+        visit(idx, field_infos, &dependencies, &mut visiting, &mut order)?;
+    }
+    Ok(order)
+}
+
+fn visit(
+    idx: usize,
+    field_infos: &[FieldInfo],
+    dependencies: &[Vec<usize>],
+    visiting: &mut Vec<usize>,
+    order: &mut Vec<usize>,
+) -> Result<(), darling::Error> {
+    if order.contains(&idx) {
+        return Ok(());
+    }
+    if let Some(start) = visiting.iter().position(|&i| i == idx) {
+        let cycle = visiting[start..]
+            .iter()
+            .chain([&idx])
+            .map(|&i| format!("`{}`", field_infos[i].display_name()))
+            .collect::<Vec<_>>()
+            .join(" -> ");
+        let field = &field_infos[idx];
+        let error = darling::Error::custom(format!(
+            "the default of `{}` depends on itself: {cycle}",
+            field.display_name()
+        ));
+        return Err(match &field.default {
+            Some(DefaultValue::Expression(expr)) => error.with_span(expr),
+            _ => error.with_span(&field.ident),
+        });
+    }
+    visiting.push(idx);
+    for &dependency in &dependencies[idx] {
+        visit(dependency, field_infos, dependencies, visiting, order)?;
+    }
+    visiting.pop();
+    order.push(idx);
+    Ok(())
+}
@@ -1,3 +1,4 @@
+use super::validate_condition::{call_args, count_limit, variant_comparison};
 use std::collections::HashMap;
 use syn::{BinOp, Expr, ExprBinary, ExprPath, ExprUnary, UnOp};
 
@@ -14,6 +15,31 @@ pub fn eval_condition(expr: &Expr, vars: &HashMap<String, bool>) -> bool {
             expr,
             ..
         }) => !eval_condition(expr, vars),
+        Expr::Binary(binary) if variant_comparison(binary).is_some() => {
+            let (field, equal, variant) = variant_comparison(binary).unwrap();
+            let chosen = vars
+                .get(&format!("{field}::{variant}"))
+                .copied()
+                .unwrap_or(false);
+
+            chosen == equal
+        }
+        Expr::Binary(ExprBinary {
+            left, op, right, ..
+        }) if call_args(left, "count").is_some() => {
+            let count = count_holding(call_args(left, "count").unwrap(), vars);
+            let limit = validated_limit(right);
+
+            match op {
+                BinOp::Eq(_) => count == limit,
+                BinOp::Ne(_) => count != limit,
+                BinOp::Lt(_) => count < limit,
+                BinOp::Le(_) => count <= limit,
+                BinOp::Gt(_) => count > limit,
+                BinOp::Ge(_) => count >= limit,
+                _ => unreachable!("conditions are validated before evaluation"),
+            }
+        }
         Expr::Binary(ExprBinary {
             left, op, right, ..
         }) => {
@@ -23,9 +49,30 @@ pub fn eval_condition(expr: &Expr, vars: &HashMap<String, bool>) -> bool {
             match op {
                 BinOp::And(_) => l && r,
                 BinOp::Or(_) => l || r,
-                _ => false,
+                BinOp::BitXor(_) => l ^ r,
+                _ => unreachable!("conditions are validated before evaluation"),
+            }
+        }
+        Expr::Call(_) => {
+            if let Some(args) = call_args(expr, "implies") {
+                !eval_condition(args[0], vars) || eval_condition(args[1], vars)
+            } else if let Some(args) = call_args(expr, "at_least") {
+                count_holding(args[1..].to_vec(), vars) >= validated_limit(args[0])
+            } else {
+                unreachable!("conditions are validated before evaluation")
             }
         }
-        _ => false,
+        _ => unreachable!("conditions are validated before evaluation"),
     }
 }
+
+fn count_holding(conditions: Vec<&Expr>, vars: &HashMap<String, bool>) -> u32 {
+    conditions
+        .into_iter()
+        .filter(|condition| eval_condition(condition, vars))
+        .count() as u32
+}
+
+fn validated_limit(expr: &Expr) -> u32 {
+    count_limit(expr).expect("conditions are validated before evaluation")
+}
@@ -0,0 +1,117 @@
+use super::{BuildTarget, FieldInfo, to_pascal_case};
+use quote::quote;
+use syn::Ident;
+
+/// Name of the error returned by `try_build` or `try_call`, e.g.
+/// `UserBuildError` for `UserBuilder`.
+pub fn build_error_name(builder_name: &Ident) -> Ident {
+    let name = builder_name.to_string();
+    let base = name.strip_suffix("Builder").unwrap_or(&name);
+    Ident::new(&format!("{base}BuildError"), builder_name.span())
+}
+
+/// Name of the error variant reported when the validator of `field` fails.
+pub fn build_error_variant(field: &FieldInfo) -> Ident {
+    Ident::new(&to_pascal_case(&field.ident), field.ident.span())
+}
+
+/// Generates the error enum of `try_build` or `try_call`, with one variant
+/// per validated field. Returns `None` when no field has a validator.
+pub fn generate_build_error(
+    field_infos: &[FieldInfo],
+    private: &proc_macro2::TokenStream,
+    builder_name: &Ident,
+    target: &BuildTarget,
+) -> Option<proc_macro2::TokenStream> {
+    let validated = field_infos
+        .iter()
+        .filter(|field| field.validate.is_some())
+        .collect::<Vec<_>>();
+    if validated.is_empty() {
+        return None;
+    }
+
+    let error_name = build_error_name(builder_name);
+    let variants = validated.iter().map(|field| build_error_variant(field));
+    let variant_docs = validated
+        .iter()
+        .map(|field| format!("`{}` was rejected by its validator.", field.display_name()));
+    let variant_fields = validated.iter().map(|field| {
+        let variant = build_error_variant(field);
+        let display_name = field.display_name();
+        quote! { Self::#variant(_) => #display_name }
+    });
+    let variant_messages = validated.iter().map(|field| {
+        let variant = build_error_variant(field);
+        let message = format!("`{}` is invalid: ", field.display_name());
+        quote! { Self::#variant(_) => #message }
+    });
+    let variant_sources = validated.iter().map(|field| {
+        let variant = build_error_variant(field);
+        quote! { Self::#variant(source) => &**source }
+    });
+    let variant_debugs = validated.iter().map(|field| {
+        let variant = build_error_variant(field);
+        let variant_name = variant.to_string();
+        quote! {
+            Self::#variant(source) => f.debug_tuple(#variant_name).field(source).finish()
+        }
+    });
+
+    let doc = format!(
+        "Error returned by `{builder_name}::try_{}` when a field of `{}` fails validation.",
+        target.finisher(),
+        target.display_name
+    );
+    let boxed_error = quote! {
+        #private::Box<dyn #private::Error + #private::Send + #private::Sync + 'static>
+    };
+
+    Some(quote! {
+        #[doc = #doc]
+        pub enum #error_name {
+            #(
+                #[doc = #variant_docs]
+                #variants(#boxed_error),
+            )*
+        }
+
+        impl #error_name {
+            /// Name of the field that failed validation.
+            pub fn field(&self) -> &'static str {
+                match self {
+                    #( #variant_fields, )*
+                }
+            }
+
+            fn source_error(&self) -> &(dyn #private::Error + #private::Send + #private::Sync + 'static) {
+                match self {
+                    #( #variant_sources, )*
+                }
+            }
+        }
+
+        impl #private::fmt::Debug for #error_name {
+            fn fmt(&self, f: &mut #private::fmt::Formatter<'_>) -> #private::fmt::Result {
+                match self {
+                    #( #variant_debugs, )*
+                }
+            }
+        }
+
+        impl #private::fmt::Display for #error_name {
+            fn fmt(&self, f: &mut #private::fmt::Formatter<'_>) -> #private::fmt::Result {
+                f.write_str(match self {
+                    #( #variant_messages, )*
+                })?;
+                #private::fmt::Display::fmt(self.source_error(), f)
+            }
+        }
+
+        impl #private::Error for #error_name {
+            fn source(&self) -> #private::Option<&(dyn #private::Error + 'static)> {
+                #private::Some(self.source_error())
+            }
+        }
+    })
+}
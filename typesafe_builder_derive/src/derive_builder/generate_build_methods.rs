@@ -1,136 +1,361 @@
-use super::eval_condition;
-use crate::input::{DefaultValue, Requirement};
-use quote::quote;
-use std::collections::HashMap;
-use syn::{Generics, Ident, Type};
-
-type FieldInfo = (Ident, Type, Requirement, Option<DefaultValue>, bool);
+use super::{
+    BuildTarget, FieldInfo, build_error_name, build_error_variant,
+    default_order::{default_dependencies, evaluation_order},
+    group_constraints::group_constraints,
+    state_constraint::{build_constraints, state_constraint, state_values},
+};
+use crate::{
+    Input,
+    input::{DefaultSource, DefaultValue, Requirement},
+};
+use proc_macro2::Span;
+use quote::{ToTokens, format_ident, quote, quote_spanned};
+use syn::{Ident, Index, Member, Path, spanned::Spanned};
 
 pub fn generate_build_methods(
     field_infos: &[FieldInfo],
+    type_params: &[Option<Ident>],
+    private: &proc_macro2::TokenStream,
+    state_module: &Ident,
     builder_name: &Ident,
-    struct_name: &Ident,
-    generics: &Generics,
+    target: &BuildTarget,
+    input: &Input,
 ) -> Vec<proc_macro2::TokenStream> {
-    let n_fields = field_infos.len();
-    let mut build_impls = Vec::new();
+    let generics = input.generics();
+    let validate_with = input.validate_with();
+    let struct_name = &target.ident;
+    let output = target.output(generics);
     let (_, ty_generics, where_clause) = generics.split_for_impl();
     let generic_params = &generics.params;
 
-    for mask in 0..(1_u32 << n_fields) {
-        if !is_mask_valid(mask, field_infos) {
-            continue;
+    let declared_type_params = type_params.iter().flatten().collect::<Vec<_>>();
+
+    // Every required or conditional field is checked through its own trait,
+    // implemented only for the states of the field allowed while its
+    // condition holds or does not. `build` is bounded on these traits, with
+    // the condition evaluated from the states of the fields it refers to, so
+    // that a violated requirement is reported with the message of its trait.
+    // Conflicts and groups are checked the same way.
+    let mut build_impls = state_values(field_infos, type_params, private, state_module);
+    let mut requirement_bounds = Vec::new();
+    for (idx, field) in field_infos.iter().enumerate() {
+        for (trait_name, constraint) in build_constraints(field) {
+            let (impls, bound) = state_constraint(
+                field_infos,
+                type_params,
+                private,
+                state_module,
+                idx,
+                trait_name,
+                constraint,
+            );
+            build_impls.extend(impls);
+            requirement_bounds.push(bound);
         }
+    }
 
-        let builder_generics = (0..n_fields)
-            .map(|i| {
-                if (mask & (1 << i)) != 0 {
-                    quote! { _TypesafeBuilderFilled }
-                } else {
-                    quote! { _TypesafeBuilderEmpty }
-                }
-            })
-            .collect::<Vec<_>>();
+    let (group_impls, group_bounds) = group_constraints(
+        &target.groups,
+        field_infos,
+        type_params,
+        private,
+        state_module,
+    );
+    build_impls.extend(group_impls);
+    requirement_bounds.extend(group_bounds);
 
-        let build_fields = field_infos
-            .iter()
-            .map(|(ident, _ty, req, default, _)| match req {
-                Requirement::Always => {
-                    quote! { #ident : self.#ident.unwrap() }
+    let members = field_infos
+        .iter()
+        .map(|field| &field.member)
+        .collect::<Vec<_>>();
+    // Values are bound to hygienic locals so that they cannot be confused
+    // with anything the validators or defaults refer to.
+    let locals = field_infos
+        .iter()
+        .map(|field| Ident::new(&field.ident.to_string(), Span::mixed_site()))
+        .collect::<Vec<_>>();
+    let mut has_computed_defaults = false;
+    let values = field_infos
+        .iter()
+        .zip(&locals)
+        .map(|(field, local)| {
+            let ident = &field.ident;
+            match (&field.requirement, &field.default) {
+                (Requirement::Always, _) | (Requirement::Default, None) => {
+                    quote! { self.#ident.unwrap() }
                 }
-                Requirement::Default => {
-                    if let Some(default_val) = default {
-                        match default_val {
-                            DefaultValue::Bare => {
-                                quote! { #ident : self.#ident.unwrap_or_else(|| Default::default()) }
-                            }
-                            DefaultValue::Expression(expr) => {
-                                quote! { #ident : self.#ident.unwrap_or_else(|| #expr) }
-                            }
-                        }
-                    } else {
-                        quote! { #ident : self.#ident.unwrap() }
-                    }
-                }
-                Requirement::Conditional(_) => {
-                    quote! { #ident : self.#ident }
+                (Requirement::Selector(_), _) if field.is_required_selector() => {
+                    quote! { self.#ident.unwrap() }
                 }
-                Requirement::Optional => quote! { #ident : self.#ident },
-                Requirement::OptionalIf(_) => {
-                    quote! { #ident : self.#ident }
+                // Conditionally required fields with a default fall back to
+                // it whenever they were not required and left unset.
+                (_, Some(DefaultValue::Bare)) => {
+                    quote! { self.#ident.unwrap_or_else(|| #private::Default::default()) }
                 }
-            });
-
-        let impl_block = if generic_params.is_empty() {
-            quote! {
-                impl #builder_name < #( #builder_generics ),* > {
-                    pub fn build(self) -> #struct_name {
-                        #struct_name {
-                            #( #build_fields, )*
-                        }
+                (_, Some(DefaultValue::Expression(expr))) => {
+                    // The fields a default refers to are computed before it
+                    // and lent to it under their own names.
+                    let dependencies = default_dependencies(field, field_infos);
+                    has_computed_defaults |= !dependencies.is_empty();
+                    let names = dependencies
+                        .iter()
+                        .map(|&i| Ident::new(&field_infos[i].ident.to_string(), Span::call_site()));
+                    let dependency_locals = dependencies.iter().map(|&i| &locals[i]);
+                    quote! {
+                        self.#ident.unwrap_or_else(|| {
+                            #(
+                                #[allow(unused_variables)]
+                                let #names = &#dependency_locals;
+                            )*
+                            #expr
+                        })
                     }
                 }
+                // Bound together by `struct_defaults` below.
+                (_, Some(DefaultValue::Struct)) => quote! { #local },
+                (
+                    Requirement::Conditional(_)
+                    | Requirement::Optional
+                    | Requirement::OptionalIf(_)
+                    | Requirement::ForbiddenIf(_)
+                    | Requirement::Flag
+                    | Requirement::Selector(_),
+                    None,
+                ) => quote! { self.#ident },
             }
-        } else {
+        })
+        .collect::<Vec<_>>();
+
+    // Fields defaulting to the struct-level `default_from` are bound first,
+    // calling it once if any of them is unset.
+    let from_struct = (0..field_infos.len())
+        .filter(|&i| matches!(field_infos[i].default, Some(DefaultValue::Struct)))
+        .collect::<Vec<_>>();
+    let struct_defaults = target
+        .default_source
+        .as_ref()
+        .filter(|_| !from_struct.is_empty())
+        .map(|source| {
+            let (source, span) = match source {
+                DefaultSource::Default(span) => (
+                    quote_spanned! {*span=>
+                        <#struct_name #ty_generics as #private::Default>::default
+                    },
+                    *span,
+                ),
+                DefaultSource::Function(path) => (
+                    resolve_self(path, quote! { #struct_name #ty_generics }),
+                    path.span(),
+                ),
+            };
+            // The fields are moved out of the default value, which a type
+            // implementing `Drop` forbids: the error points at the attribute.
+            let defaults = Ident::new("defaults", Span::mixed_site().located_at(span));
+            let bound_locals = from_struct.iter().map(|&i| &locals[i]).collect::<Vec<_>>();
+            let idents = from_struct.iter().map(|&i| &field_infos[i].ident);
+            let bound_members = from_struct.iter().map(|&i| match &field_infos[i].member {
+                Member::Named(ident) => {
+                    let mut ident = ident.clone();
+                    ident.set_span(ident.span().located_at(span));
+                    Member::Named(ident)
+                }
+                Member::Unnamed(index) => Member::Unnamed(Index {
+                    index: index.index,
+                    span,
+                }),
+            });
             quote! {
-                impl < #generic_params > #builder_name < #generic_params, #( #builder_generics ),* > #where_clause {
-                    pub fn build(self) -> #struct_name #ty_generics {
-                        #struct_name {
-                            #( #build_fields, )*
-                        }
+                let ( #( #bound_locals, )* ) = match ( #( self.#idents, )* ) {
+                    ( #( #private::Some(#bound_locals), )* ) => ( #( #bound_locals, )* ),
+                    ( #( #bound_locals, )* ) => {
+                        let #defaults = #source();
+                        ( #( #bound_locals.unwrap_or(#defaults.#bound_members), )* )
                     }
-                }
+                };
             }
-        };
-
-        build_impls.push(impl_block);
+        });
+    // A generic struct may only implement `Default` for some arguments.
+    if let Some(DefaultSource::Default(span)) = &target.default_source
+        && !from_struct.is_empty()
+        && !generic_params.is_empty()
+    {
+        requirement_bounds
+            .push(quote_spanned! {*span=> #struct_name #ty_generics: #private::Default });
     }
 
-    build_impls
-}
+    // Fields are computed after the fields their defaults refer to.
+    let order = evaluation_order(field_infos)
+        .expect("default cycles are rejected beforehand")
+        .into_iter()
+        .filter(|i| !from_struct.contains(i))
+        .collect::<Vec<_>>();
+    let ordered_locals = order.iter().map(|&i| &locals[i]).collect::<Vec<_>>();
+    let ordered_values = order.iter().map(|&i| &values[i]).collect::<Vec<_>>();
 
-fn is_mask_valid(mask: u32, field_infos: &[FieldInfo]) -> bool {
-    let _n_fields = field_infos.len();
-    let mut var_map = HashMap::<String, bool>::new();
+    // A function is called with the same qualifiers as its own.
+    let (asyncness, unsafety) = target
+        .call
+        .as_ref()
+        .map(|function| (function.sig.asyncness, function.sig.unsafety))
+        .unwrap_or_default();
+    let finisher = Ident::new(target.finisher(), Span::call_site());
 
-    for (idx, (ident, _, _, _, _)) in field_infos.iter().enumerate() {
-        var_map.insert(ident.to_string(), (mask & (1 << idx)) != 0);
-    }
+    let finish = target.finish(&members, &locals);
+    let has_field_validators = field_infos.iter().any(|field| field.validate.is_some());
+    let build_method = if has_field_validators || validate_with.is_some() {
+        let error_name = build_error_name(builder_name);
+        let checks = field_infos
+            .iter()
+            .zip(locals.iter())
+            .filter_map(|(field, local)| {
+                let validate = field.validate.as_ref()?;
+                let variant = build_error_variant(field);
+                let value = Ident::new("value", Span::mixed_site());
+                // With a struct-level validator, `build` fails with its error
+                // type, which field errors are converted into.
+                let error = match validate_with {
+                    Some(_) => quote! {
+                        #private::Into::into(#error_name::#variant(#private::Into::into(error)))
+                    },
+                    None => quote! { #error_name::#variant(#private::Into::into(error)) },
+                };
+                let check = quote! {
+                    if let #private::Err(error) = #validate(#value) {
+                        return #private::Err(#error);
+                    }
+                };
+                Some(match field.requirement {
+                    Requirement::Always | Requirement::Default | Requirement::Flag => quote! {
+                        let #value = &#local;
+                        #check
+                    },
+                    Requirement::Selector(_) if field.is_required_selector() => quote! {
+                        let #value = &#local;
+                        #check
+                    },
+                    _ if field.default.is_some() => quote! {
+                        let #value = &#local;
+                        #check
+                    },
+                    Requirement::Optional
+                    | Requirement::Selector(_)
+                    | Requirement::Conditional(_)
+                    | Requirement::OptionalIf(_)
+                    | Requirement::ForbiddenIf(_) => quote! {
+                        if let #private::Some(#value) = &#local {
+                            #check
+                        }
+                    },
+                })
+            })
+            .collect::<Vec<_>>();
 
-    for (idx, (_, _, req, _default, _)) in field_infos.iter().enumerate() {
-        match req {
-            Requirement::Always => {
-                if (mask & (1 << idx)) == 0 {
-                    return false;
-                }
+        let built = Ident::new("built", Span::mixed_site());
+        let (method_name, error_ty, struct_check, doc) = match validate_with {
+            Some((validator, error_ty)) => {
+                let validator_name = validator.to_token_stream().to_string().replace(' ', "");
+                let validator = resolve_self(validator, quote! { #struct_name #ty_generics });
+                let doc = format!(
+                    "Builds `{}` and checks it with `{validator_name}`, failing with its error.",
+                    target.display_name
+                );
+                let struct_check = quote! {
+                    if let #private::Err(error) = #validator(&#built) {
+                        return #private::Err(#private::Into::into(error));
+                    }
+                };
+                (quote! { build }, quote! { #error_ty }, struct_check, doc)
             }
-            Requirement::Default => {
-                // Default fields can always be built (they have default values)
+            None => {
+                let doc = format!(
+                    "{} `{}`, running the validators of its fields. Fails with the first field whose validator rejects its value.",
+                    match target.call {
+                        Some(_) => "Calls",
+                        None => "Builds",
+                    },
+                    target.display_name
+                );
+                let method_name = format_ident!("try_{}", finisher);
+                (
+                    quote! { #method_name },
+                    quote! { #error_name },
+                    quote! {},
+                    doc,
+                )
             }
-            Requirement::Conditional(expr) => {
-                let mut cond_vars = var_map.clone();
-                let field_name = field_infos[idx].0.to_string();
-                cond_vars.remove(&field_name);
-                let needed = eval_condition(expr, &cond_vars);
-                let filled = (mask & (1 << idx)) != 0;
-                if needed && !filled {
-                    return false;
-                }
+        };
+
+        quote! {
+            #[doc = #doc]
+            pub #asyncness #unsafety fn #method_name(self) -> #private::Result<#output, #error_ty>
+            where
+                #( #requirement_bounds, )*
+            {
+                #struct_defaults
+                #( let #ordered_locals = #ordered_values; )*
+                #( #checks )*
+                let #built = #finish;
+                #struct_check
+                #private::Ok(#built)
             }
-            Requirement::Optional => {}
-            Requirement::OptionalIf(expr) => {
-                let mut cond_vars = var_map.clone();
-                let field_name = field_infos[idx].0.to_string();
-                cond_vars.remove(&field_name);
-                let is_optional = eval_condition(expr, &cond_vars);
-                let filled = (mask & (1 << idx)) != 0;
-
-                if !is_optional && !filled {
-                    return false;
-                }
+        }
+    } else if has_computed_defaults || struct_defaults.is_some() || target.call.is_some() {
+        quote! {
+            pub #asyncness #unsafety fn #finisher(self) -> #output
+            where
+                #( #requirement_bounds, )*
+            {
+                #struct_defaults
+                #( let #ordered_locals = #ordered_values; )*
+                #finish
             }
         }
-    }
+    } else {
+        let finish = target.finish(&members, &values);
+        quote! {
+            pub fn build(self) -> #output
+            where
+                #( #requirement_bounds, )*
+            {
+                #finish
+            }
+        }
+    };
 
-    true
+    let where_predicates = where_clause
+        .map(|where_clause| where_clause.predicates.iter().collect::<Vec<_>>())
+        .unwrap_or_default();
+
+    let impl_block = if generic_params.is_empty() {
+        quote! {
+            impl < #( #declared_type_params ),* > #builder_name < #( #declared_type_params ),* > {
+                #build_method
+            }
+        }
+    } else {
+        quote! {
+            impl < #generic_params, #( #declared_type_params ),* > #builder_name < #generic_params, #( #declared_type_params ),* >
+            where
+                #( #where_predicates, )*
+            {
+                #build_method
+            }
+        }
+    };
+
+    build_impls.push(impl_block);
+
+    build_impls
+}
+
+/// `Self` inside the builder refers to the builder, so a validator given as
+/// `Self::check` is resolved against the built type instead.
+fn resolve_self(path: &Path, target_ty: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+    match path.segments.first() {
+        Some(first) if path.leading_colon.is_none() && first.ident == "Self" => {
+            let rest = path.segments.iter().skip(1);
+            quote! { < #target_ty > #( :: #rest )* }
+        }
+        _ => path.to_token_stream(),
+    }
 }
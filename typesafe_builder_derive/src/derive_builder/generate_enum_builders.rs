@@ -0,0 +1,84 @@
+use super::{BuildTarget, generate_builder, to_snake_case};
+use crate::{Input, input::InputVariant};
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::Ident;
+
+/// Generates one builder per variant, plus an entry type named after the
+/// enum's builder with one constructor per variant, e.g.
+/// `CommandBuilder::create()` returning a fresh `CommandCreateBuilder`.
+pub fn generate_enum_builders(
+    input: &Input,
+    variants: &[&InputVariant],
+) -> Result<TokenStream2, darling::Error> {
+    let name = input.ident();
+    let builder_name = Ident::new(&input.builder_name(), name.span());
+    let (_, _, where_clause) = input.generics().split_for_impl();
+    let generic_params = &input.generics().params;
+
+    let mut errors = darling::Error::accumulator();
+    let mut variant_builders = Vec::new();
+    let mut constructors = Vec::new();
+
+    for variant in variants {
+        let variant_ident = variant.ident();
+        let variant_builder_name = Ident::new(&variant.builder_name(name), variant_ident.span());
+        let target = BuildTarget {
+            ident: name.clone(),
+            display_name: format!("{name}::{variant_ident}"),
+            constructor: quote! { #name::#variant_ident },
+            groups: variant.groups().to_vec(),
+            conditions: variant.conditions().cloned().collect(),
+            default_source: None,
+            default_requirement: variant
+                .default_requirement()
+                .or(input.default_requirement()),
+            call: None,
+        };
+        let fields = variant.fields().iter().collect::<Vec<_>>();
+
+        let Some((builder, initial_builder_ty)) = errors.handle(generate_builder(
+            input,
+            &fields,
+            &variant_builder_name,
+            &target,
+        )) else {
+            continue;
+        };
+
+        let constructor_name = constructor_name(variant_ident);
+        let doc = format!("Starts building `{name}::{variant_ident}`.");
+        constructors.push(quote! {
+            #[doc = #doc]
+            #[inline]
+            pub fn #constructor_name < #generic_params > () -> #initial_builder_ty #where_clause {
+                #variant_builder_name::new()
+            }
+        });
+        variant_builders.push(builder);
+    }
+
+    errors.finish()?;
+
+    let doc = format!("Entry point of the builders of each `{name}` variant.");
+    Ok(quote! {
+        #( #variant_builders )*
+
+        #[doc = #doc]
+        pub struct #builder_name;
+
+        impl #builder_name {
+            #( #constructors )*
+        }
+    })
+}
+
+/// Snake case name of a variant constructor, made raw when it is a keyword
+/// such as `r#move`.
+fn constructor_name(variant_ident: &Ident) -> Ident {
+    let snake = to_snake_case(variant_ident);
+    match syn::parse_str::<Ident>(&snake) {
+        Ok(_) => Ident::new(&snake, variant_ident.span()),
+        Err(_) => Ident::new_raw(&snake, variant_ident.span()),
+    }
+}
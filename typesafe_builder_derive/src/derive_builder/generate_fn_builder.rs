@@ -0,0 +1,676 @@
+use super::{BuildTarget, FunctionCall, generate_builder, to_pascal_case};
+use crate::{Input, input::DefaultRequirement};
+use darling::{FromDeriveInput, ast::Data};
+use proc_macro2::{Group, Span, TokenStream as TokenStream2, TokenTree};
+use quote::{ToTokens, format_ident, quote};
+use syn::{
+    Attribute, FnArg, GenericArgument, GenericParam, Generics, Ident, ImplItem, Item, ItemFn,
+    ItemImpl, Lifetime, LifetimeParam, Meta, Pat, PathArguments, ReturnType, Signature, Stmt, Type,
+    Visibility, WherePredicate, ext::IdentExt, parse_quote, spanned::Spanned,
+};
+
+/// Generates a builder collecting the arguments of `function`, replacing it
+/// with a function of the same name starting the builder, e.g. `connect()`
+/// returning a fresh `ConnectBuilder` whose `call` calls the original
+/// `connect`. `attr` holds the options of `#[builder(...)]` on the function,
+/// which are those of a struct.
+pub fn generate_fn_builder(
+    attr: TokenStream2,
+    function: ItemFn,
+) -> Result<TokenStream2, darling::Error> {
+    // A function of an `impl` block reaches the attribute on its own, which
+    // only `Self` gives away, since a free function cannot refer to it.
+    let outside_items = function
+        .block
+        .stmts
+        .iter()
+        .filter(|stmt| !matches!(stmt, Stmt::Item(_)));
+    let self_span = find_self(function.sig.to_token_stream()).or_else(|| {
+        outside_items
+            .map(|stmt| stmt.to_token_stream())
+            .find_map(find_self)
+    });
+    if let Some(span) = self_span {
+        return Err(darling::Error::custom(
+            "`#[builder]` on an associated function also needs `#[builder]` on its `impl` block",
+        )
+        .with_span(&span));
+    }
+    let sig = &function.sig;
+    // The original function is kept as is, minus the attributes of its
+    // arguments, and declared inside `call`.
+    let mut inner = function.clone();
+    strip_argument_attributes(&mut inner.sig);
+    let ident = &sig.ident;
+    let (builder, entry) = generate_call_builder(Function {
+        options: attr,
+        attrs: &function.attrs,
+        vis: &function.vis,
+        sig,
+        impl_block: None,
+        display_name: ident.to_string(),
+        name: Ident::new(&to_pascal_case(ident), ident.span()),
+        entry: ident.clone(),
+        path: quote! { #ident },
+        item: Some(inner),
+    })?;
+    Ok(quote! {
+        #builder
+
+        #entry
+    })
+}
+
+/// Generates the builders of the functions of `impl_block` marked with
+/// `#[builder]`, including methods taking `self`. `Type::new` gets its builder
+/// started by `Type::builder()` and is kept as is, while any other function
+/// is replaced with one starting its builder, e.g. `Pool::query` returning a
+/// `PoolQueryBuilder`, and kept under a private name for `call`.
+pub fn generate_impl_builders(
+    attr: TokenStream2,
+    mut impl_block: ItemImpl,
+) -> Result<TokenStream2, darling::Error> {
+    if !attr.is_empty() {
+        return Err(darling::Error::custom(
+            "options of `#[builder(...)]` go on the functions of the `impl` block",
+        )
+        .with_span(&attr));
+    }
+    if let Some((_, path, _)) = &impl_block.trait_ {
+        return Err(darling::Error::custom(
+            "`#[builder]` is only supported on inherent `impl` blocks",
+        )
+        .with_span(path));
+    }
+    let self_ident = match &*impl_block.self_ty {
+        Type::Path(path) if path.qself.is_none() => path.path.segments.last(),
+        _ => None,
+    }
+    .map(|segment| segment.ident.clone())
+    .ok_or_else(|| {
+        darling::Error::custom("`#[builder]` is only supported on `impl` blocks of named types")
+            .with_span(&impl_block.self_ty)
+    })?;
+
+    let block = impl_block.clone();
+    let self_ty = &block.self_ty;
+    let mut errors = darling::Error::accumulator();
+    let mut builders = Vec::new();
+    let mut items = Vec::new();
+    for item in std::mem::take(&mut impl_block.items) {
+        let ImplItem::Fn(mut method) = item else {
+            items.push(item);
+            continue;
+        };
+        let Some(position) = method
+            .attrs
+            .iter()
+            .position(|attr| attr.path().is_ident("builder"))
+        else {
+            items.push(ImplItem::Fn(method));
+            continue;
+        };
+        let attr = method.attrs.remove(position);
+        let options = match attr.meta {
+            Meta::Path(_) => TokenStream2::new(),
+            Meta::List(list) => list.tokens,
+            Meta::NameValue(name_value) => {
+                errors.push(
+                    darling::Error::custom("expected `#[builder]` or `#[builder(...)]`")
+                        .with_span(&name_value),
+                );
+                continue;
+            }
+        };
+
+        let ident = method.sig.ident.clone();
+        let is_new = ident == "new";
+        let (name, entry, callee) = if is_new {
+            (
+                self_ident.clone(),
+                Ident::new("builder", ident.span()),
+                ident.clone(),
+            )
+        } else {
+            (
+                format_ident!("{}{}", self_ident, to_pascal_case(&ident)),
+                ident.clone(),
+                format_ident!("__{}", ident),
+            )
+        };
+        let Some((builder, entry)) = errors.handle(generate_call_builder(Function {
+            options,
+            attrs: &method.attrs,
+            vis: &method.vis,
+            sig: &method.sig,
+            impl_block: Some(&block),
+            display_name: format!("{self_ident}::{ident}"),
+            name,
+            entry,
+            path: quote! { <#self_ty>::#callee },
+            item: None,
+        })) else {
+            continue;
+        };
+        builders.push(builder);
+
+        strip_argument_attributes(&mut method.sig);
+        if !is_new {
+            method.sig.ident = callee;
+            method.vis = Visibility::Inherited;
+        }
+        items.push(ImplItem::Fn(method));
+        items.push(ImplItem::Verbatim(entry));
+    }
+    errors.finish()?;
+    impl_block.items = items;
+
+    Ok(quote! {
+        #impl_block
+
+        #( #builders )*
+    })
+}
+
+/// A function to generate a builder for.
+struct Function<'a> {
+    /// Options given with `#[builder(...)]` on the function.
+    options: TokenStream2,
+    attrs: &'a [Attribute],
+    vis: &'a Visibility,
+    sig: &'a Signature,
+    /// The `impl` block of an associated function.
+    impl_block: Option<&'a ItemImpl>,
+    /// Name used in diagnostics, e.g. `connect` or `Pool::new`.
+    display_name: String,
+    /// Name of the struct the arguments are read as, after which the builder
+    /// is named, e.g. `Connect` for `ConnectBuilder`.
+    name: Ident,
+    /// Name of the function starting the builder.
+    entry: Ident,
+    /// Path `call` calls the function by.
+    path: TokenStream2,
+    /// A free function, declared inside `call`.
+    item: Option<ItemFn>,
+}
+
+/// Generates the builder of `function` and the function starting it.
+fn generate_call_builder(
+    function: Function,
+) -> Result<(TokenStream2, TokenStream2), darling::Error> {
+    let sig = function.sig;
+    if let Some(variadic) = &sig.variadic {
+        return Err(
+            darling::Error::custom("variadic functions are not supported by `#[builder]`")
+                .with_span(variadic),
+        );
+    }
+    let self_ty = function.impl_block.map(|impl_block| &*impl_block.self_ty);
+
+    let mut errors = darling::Error::accumulator();
+    // The type of the receiver as written on the function and as stored by
+    // the builder, with an elided lifetime named `'receiver`.
+    let mut receiver = None;
+    let mut receiver_lifetime = None;
+    // Lifetimes the arguments elide, named after their argument, e.g.
+    // `'host` for `host: &str`, since the builder stores the arguments.
+    let mut taken = function
+        .impl_block
+        .into_iter()
+        .flat_map(|impl_block| impl_block.generics.lifetimes())
+        .chain(sig.generics.lifetimes())
+        .map(|param| param.lifetime.ident.to_string())
+        .chain(["receiver".to_string()])
+        .collect::<Vec<_>>();
+    let mut arg_lifetimes = Vec::new();
+    let mut arg_types = Vec::new();
+    let mut fields = Vec::new();
+    for input in &sig.inputs {
+        let arg = match input {
+            FnArg::Typed(arg) => arg,
+            FnArg::Receiver(arg) => {
+                let Some(self_ty) = self_ty else {
+                    errors.push(
+                        darling::Error::custom(
+                            "`#[builder]` on a method also needs `#[builder]` on its `impl` block",
+                        )
+                        .with_span(arg),
+                    );
+                    continue;
+                };
+                let mut ty = (*arg.ty).clone();
+                if let Type::Reference(reference) = &mut ty
+                    && reference
+                        .lifetime
+                        .as_ref()
+                        .is_none_or(|lifetime| lifetime.ident == "_")
+                {
+                    let lifetime = Lifetime::new("'receiver", arg.span());
+                    reference.lifetime = Some(lifetime.clone());
+                    receiver_lifetime = Some(lifetime);
+                }
+                let stored = replace_self_in_type(&ty, self_ty);
+                if let Some(error) = unsupported_type(&stored) {
+                    errors.push(error);
+                }
+                receiver = Some((ty, stored));
+                continue;
+            }
+        };
+        let ident = match &*arg.pat {
+            Pat::Ident(pat) if pat.by_ref.is_none() && pat.subpat.is_none() => &pat.ident,
+            pat => {
+                errors.push(
+                    darling::Error::custom(
+                        "arguments of a `#[builder]` function must be plain names, e.g. `host: String`",
+                    )
+                    .with_span(pat),
+                );
+                continue;
+            }
+        };
+        if let Some(error) = unsupported_type(&arg.ty) {
+            errors.push(error);
+            continue;
+        }
+        let mut ty = (*arg.ty).clone();
+        name_elided_lifetimes(&mut ty, &mut || {
+            let base = ident.unraw().to_string();
+            let name = std::iter::once(base.clone())
+                .chain((1..).map(|n| format!("{base}_{n}")))
+                .find(|name| !taken.contains(name))
+                .expect("an unused name is eventually found");
+            taken.push(name.clone());
+            let lifetime = Lifetime::new(&format!("'{name}"), ident.span());
+            arg_lifetimes.push(lifetime.clone());
+            lifetime
+        });
+        let attrs = arg
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("builder"));
+        fields.push(quote! { #( #attrs )* #ident : #ty });
+        arg_types.push(ty);
+    }
+    errors.finish()?;
+
+    // The builder is generic over the parameters of the `impl` block and of
+    // the function, plus the lifetime of the receiver, lifetimes first.
+    let impl_generics = function
+        .impl_block
+        .map(|impl_block| split_bounds(&impl_block.generics))
+        .transpose()?
+        .unwrap_or_default();
+    let fn_generics = split_bounds(&sig.generics)?;
+    let named_params = receiver_lifetime
+        .into_iter()
+        .chain(arg_lifetimes)
+        .map(|lifetime| GenericParam::Lifetime(LifetimeParam::new(lifetime)))
+        .collect::<Vec<_>>();
+    let (lifetimes, others): (Vec<_>, Vec<_>) = impl_generics
+        .params
+        .iter()
+        .chain(&named_params)
+        .chain(&fn_generics.params)
+        .cloned()
+        .partition(|param| matches!(param, GenericParam::Lifetime(_)));
+    let mut generics = Generics::default();
+    generics.params.extend(lifetimes.into_iter().chain(others));
+    generics.make_where_clause().predicates.extend(
+        [&impl_generics, &fn_generics]
+            .into_iter()
+            .flat_map(|generics| generics.where_clause.iter())
+            .flat_map(|where_clause| where_clause.predicates.iter().cloned()),
+    );
+
+    // The options and arguments are read the way they would be on a struct
+    // holding the arguments as fields, declared outside of the `impl` block.
+    let name = &function.name;
+    let (_, _, where_clause) = generics.split_for_impl();
+    let params = &generics.params;
+    let options = &function.options;
+    let options = (!options.is_empty()).then(|| quote! { #[builder(#options)] });
+    let tokens = quote! {
+        #options
+        struct #name < #params > #where_clause {
+            #( #fields, )*
+        }
+    };
+    let tokens = match self_ty {
+        Some(self_ty) => replace_self(tokens, self_ty),
+        None => tokens,
+    };
+    let derive_input = syn::parse2(tokens)?;
+    let input = Input::from_derive_input(&derive_input)?;
+    if let Some((validate_with, _)) = input.validate_with() {
+        return Err(darling::Error::custom(
+            "`validate_with` is not supported on functions, which can check their arguments themselves",
+        )
+        .with_span(validate_with));
+    }
+    if input.default_source().is_some() {
+        return Err(
+            darling::Error::custom("`default_from` is only supported on structs")
+                .with_span(&sig.ident),
+        );
+    }
+    let Data::Struct(input_fields) = input.data() else {
+        unreachable!("a struct is parsed")
+    };
+
+    // Lifetimes the return type elides are those of a borrowed receiver, or
+    // else the only lifetime of the arguments.
+    let mut call_sig = sig.clone();
+    if let ReturnType::Type(_, output) = &mut call_sig.output {
+        if let Some(self_ty) = self_ty {
+            **output = replace_self_in_type(output, self_ty);
+        }
+        let elided = match &receiver {
+            Some((Type::Reference(reference), _)) => reference.lifetime.clone(),
+            _ => {
+                let mut found = Vec::new();
+                for ty in receiver.iter().map(|(_, stored)| stored).chain(&arg_types) {
+                    collect_lifetimes(ty.to_token_stream(), &mut found);
+                }
+                match found.split_first() {
+                    Some((first, rest)) if rest.iter().all(|other| other.ident == first.ident) => {
+                        Some(first.clone())
+                    }
+                    _ => None,
+                }
+            }
+        };
+        if let Some(lifetime) = elided {
+            name_elided_lifetimes(output, &mut || lifetime.clone());
+        }
+    }
+    let builder_name = Ident::new(&input.builder_name(), sig.ident.span());
+    let target = BuildTarget {
+        ident: name.clone(),
+        display_name: function.display_name,
+        constructor: quote! {},
+        groups: input.groups().to_vec(),
+        conditions: input.conditions().cloned().collect(),
+        default_source: None,
+        // Unannotated arguments are required, or optional if of type
+        // `Option<T>`, unless the function declares otherwise.
+        default_requirement: input
+            .default_requirement()
+            .or(Some(DefaultRequirement::Infer)),
+        call: Some(FunctionCall {
+            sig: call_sig,
+            path: function.path,
+            item: function.item,
+            receiver: receiver.as_ref().map(|(_, stored)| stored.clone()),
+        }),
+    };
+    let (builder, initial_builder_ty) =
+        generate_builder(&input, &input_fields.fields, &builder_name, &target)?;
+    let builder = forward_attributes(builder, &builder_name, function.attrs)?;
+
+    let vis = function.vis;
+    let entry = &function.entry;
+    // The function starting the builder is the one callers see, so it is
+    // documented, deprecated or configured out like the original. Discarding
+    // what it returns is reported by the builder struct, if `must_use`.
+    let attrs = function.attrs.iter().filter(|attr| {
+        ["doc", "deprecated"]
+            .iter()
+            .any(|name| attr.path().is_ident(name))
+            || is_forwarded(attr, FORWARDED_TO_ITEMS)
+    });
+    let allow_deprecated = is_deprecated(function.attrs).then(|| quote! { #[allow(deprecated)] });
+    let entry_params = named_params.iter().chain(&fn_generics.params);
+    let (_, _, entry_where_clause) = fn_generics.split_for_impl();
+    let entry_receiver = receiver.as_ref().map(|(ty, _)| quote! { self: #ty });
+    let receiver_arg = receiver.as_ref().map(|_| quote! { self });
+    let entry = quote! {
+        #( #attrs )*
+        #allow_deprecated
+        #[inline]
+        #vis fn #entry < #( #entry_params ),* > ( #entry_receiver ) -> #initial_builder_ty #entry_where_clause {
+            #builder_name::new(#receiver_arg)
+        }
+    };
+    Ok((builder, entry))
+}
+
+/// Attributes of the function every item of its builder gets.
+const FORWARDED_TO_ITEMS: &[&str] = &["cfg", "allow", "warn", "deny", "forbid"];
+
+/// Attributes of the function the builder struct gets, besides those every
+/// item gets.
+const FORWARDED_TO_STRUCT: &[&str] = &["deprecated", "must_use"];
+
+fn is_forwarded(attr: &Attribute, names: &[&str]) -> bool {
+    names.iter().any(|name| attr.path().is_ident(name))
+}
+
+fn is_deprecated(attrs: &[Attribute]) -> bool {
+    attrs.iter().any(|attr| attr.path().is_ident("deprecated"))
+}
+
+/// Gives the items of `builder` the attributes of the function that apply to
+/// them, so that the builder exists under the same `cfg`, with the same lint
+/// levels, and is deprecated or `must_use` like the function.
+fn forward_attributes(
+    builder: TokenStream2,
+    builder_name: &Ident,
+    attrs: &[Attribute],
+) -> Result<TokenStream2, darling::Error> {
+    let for_items = attrs
+        .iter()
+        .filter(|attr| is_forwarded(attr, FORWARDED_TO_ITEMS))
+        .collect::<Vec<_>>();
+    let for_struct = attrs
+        .iter()
+        .filter(|attr| is_forwarded(attr, FORWARDED_TO_STRUCT))
+        .collect::<Vec<_>>();
+    if for_items.is_empty() && for_struct.is_empty() {
+        return Ok(builder);
+    }
+    // Only uses of the builder outside of it warn.
+    let allow_deprecated = is_deprecated(attrs).then(|| parse_quote! { #[allow(deprecated)] });
+    let mut file = syn::parse2::<syn::File>(builder)?;
+    for item in &mut file.items {
+        let item_attrs = match item {
+            Item::Struct(item) if item.ident == *builder_name => {
+                item.attrs.extend(for_struct.iter().copied().cloned());
+                item.attrs.extend(for_items.iter().copied().cloned());
+                continue;
+            }
+            Item::Struct(item) => &mut item.attrs,
+            Item::Enum(item) => &mut item.attrs,
+            Item::Impl(item) => &mut item.attrs,
+            Item::Mod(item) => &mut item.attrs,
+            Item::Fn(item) => &mut item.attrs,
+            Item::Trait(item) => &mut item.attrs,
+            _ => continue,
+        };
+        item_attrs.extend(for_items.iter().copied().cloned());
+        item_attrs.extend(allow_deprecated.clone());
+    }
+    Ok(file.into_token_stream())
+}
+
+/// Removes the `#[builder(...)]` attributes of the arguments of the original
+/// function.
+fn strip_argument_attributes(sig: &mut Signature) {
+    for input in &mut sig.inputs {
+        if let FnArg::Typed(arg) = input {
+            arg.attrs.retain(|attr| !attr.path().is_ident("builder"));
+        }
+    }
+}
+
+/// Span of the first `Self` in `tokens`.
+fn find_self(tokens: TokenStream2) -> Option<Span> {
+    tokens.into_iter().find_map(|token| match token {
+        TokenTree::Ident(ident) if ident == "Self" => Some(ident.span()),
+        TokenTree::Group(group) => find_self(group.stream()),
+        _ => None,
+    })
+}
+
+/// Replaces `Self`, which refers to the type of the `impl` block, with that
+/// type, for use outside of the block.
+fn replace_self(tokens: TokenStream2, self_ty: &Type) -> TokenStream2 {
+    let mut replaced = TokenStream2::new();
+    let mut tokens = tokens.into_iter().peekable();
+    while let Some(token) = tokens.next() {
+        match token {
+            TokenTree::Ident(ident) if ident == "Self" => {
+                // `Self::Item` becomes `<Type>::Item`.
+                let is_qualified = matches!(
+                    tokens.peek(),
+                    Some(TokenTree::Punct(punct))
+                        if punct.as_char() == ':' && punct.spacing() == proc_macro2::Spacing::Joint
+                );
+                if is_qualified {
+                    replaced.extend(quote! { < #self_ty > });
+                } else {
+                    self_ty.to_tokens(&mut replaced);
+                }
+            }
+            TokenTree::Group(group) => {
+                let mut inner =
+                    Group::new(group.delimiter(), replace_self(group.stream(), self_ty));
+                inner.set_span(group.span());
+                replaced.extend([TokenTree::Group(inner)]);
+            }
+            token => replaced.extend([token]),
+        }
+    }
+    replaced
+}
+
+/// Gives each lifetime `ty` elides the name `name` returns.
+fn name_elided_lifetimes(ty: &mut Type, name: &mut impl FnMut() -> Lifetime) {
+    let is_elided =
+        |found: &Option<Lifetime>| found.as_ref().is_none_or(|found| found.ident == "_");
+    match ty {
+        Type::Reference(reference) => {
+            if is_elided(&reference.lifetime) {
+                reference.lifetime = Some(name());
+            }
+            name_elided_lifetimes(&mut reference.elem, name);
+        }
+        Type::Path(path) => {
+            for segment in &mut path.path.segments {
+                let PathArguments::AngleBracketed(args) = &mut segment.arguments else {
+                    continue;
+                };
+                for arg in &mut args.args {
+                    match arg {
+                        GenericArgument::Lifetime(found) if found.ident == "_" => {
+                            *found = name();
+                        }
+                        GenericArgument::Type(ty) => name_elided_lifetimes(ty, name),
+                        _ => {}
+                    }
+                }
+            }
+        }
+        Type::Tuple(tuple) => {
+            for elem in &mut tuple.elems {
+                name_elided_lifetimes(elem, name);
+            }
+        }
+        Type::Array(array) => name_elided_lifetimes(&mut array.elem, name),
+        Type::Slice(slice) => name_elided_lifetimes(&mut slice.elem, name),
+        Type::Ptr(ptr) => name_elided_lifetimes(&mut ptr.elem, name),
+        Type::Paren(paren) => name_elided_lifetimes(&mut paren.elem, name),
+        Type::Group(group) => name_elided_lifetimes(&mut group.elem, name),
+        _ => {}
+    }
+}
+
+/// Pushes the lifetimes written in `tokens` onto `found`.
+fn collect_lifetimes(tokens: TokenStream2, found: &mut Vec<Lifetime>) {
+    let mut tokens = tokens.into_iter().peekable();
+    while let Some(token) = tokens.next() {
+        match token {
+            TokenTree::Punct(punct) if punct.as_char() == '\'' => {
+                if let Some(TokenTree::Ident(ident)) = tokens.next() {
+                    found.push(Lifetime::new(&format!("'{ident}"), ident.span()));
+                }
+            }
+            TokenTree::Group(group) => collect_lifetimes(group.stream(), found),
+            _ => {}
+        }
+    }
+}
+
+fn replace_self_in_type(ty: &Type, self_ty: &Type) -> Type {
+    syn::parse2(replace_self(ty.to_token_stream(), self_ty)).expect("replacing `Self` keeps a type")
+}
+
+/// Moves the bounds of the generic parameters into the `where` clause, so
+/// that the parameters can also be written as arguments of the builder.
+fn split_bounds(generics: &Generics) -> Result<Generics, darling::Error> {
+    let mut split = generics.clone();
+    let mut predicates = Vec::<WherePredicate>::new();
+    for param in &mut split.params {
+        match param {
+            GenericParam::Lifetime(param) => {
+                let lifetime = &param.lifetime;
+                let bounds = std::mem::take(&mut param.bounds);
+                if !bounds.is_empty() {
+                    predicates.push(parse_quote! { #lifetime: #bounds });
+                }
+                param.colon_token = None;
+            }
+            GenericParam::Type(param) => {
+                let ident = &param.ident;
+                let bounds = std::mem::take(&mut param.bounds);
+                if !bounds.is_empty() {
+                    predicates.push(parse_quote! { #ident: #bounds });
+                }
+                param.colon_token = None;
+            }
+            GenericParam::Const(param) => {
+                return Err(darling::Error::custom(
+                    "const generic parameters are not supported by `#[builder]`",
+                )
+                .with_span(param));
+            }
+        }
+    }
+    split.make_where_clause().predicates.extend(predicates);
+    Ok(split)
+}
+
+/// Reports the first `impl Trait` in the type of an argument, which the
+/// builder cannot store.
+fn unsupported_type(ty: &Type) -> Option<darling::Error> {
+    match ty {
+        Type::ImplTrait(_) => Some(
+            darling::Error::custom(
+                "`impl Trait` arguments are not supported by `#[builder]`, declare a type parameter instead",
+            )
+            .with_span(ty),
+        ),
+        Type::Reference(reference) => unsupported_type(&reference.elem),
+        Type::Path(path) => {
+            let qself = path.qself.as_ref().and_then(|qself| unsupported_type(&qself.ty));
+            qself.or_else(|| {
+                path.path.segments.iter().find_map(|segment| {
+                    let PathArguments::AngleBracketed(args) = &segment.arguments else {
+                        return None;
+                    };
+                    args.args.iter().find_map(|arg| match arg {
+                        GenericArgument::Type(ty) => unsupported_type(ty),
+                        _ => None,
+                    })
+                })
+            })
+        }
+        Type::Tuple(tuple) => tuple.elems.iter().find_map(unsupported_type),
+        Type::Array(array) => unsupported_type(&array.elem),
+        Type::Slice(slice) => unsupported_type(&slice.elem),
+        Type::Ptr(ptr) => unsupported_type(&ptr.elem),
+        Type::Paren(paren) => unsupported_type(&paren.elem),
+        Type::Group(group) => unsupported_type(&group.elem),
+        _ => None,
+    }
+}
@@ -1,102 +1,241 @@
-use crate::{
-    derive_builder::extract_arg_type,
-    input::{DefaultValue, Requirement},
+use super::{
+    FieldInfo, extract_arg_type, selector_enum, state_constraint::state_constraint, to_pascal_case,
 };
+use crate::input::Requirement;
 use quote::quote;
-use syn::{Generics, Ident, Type};
+use syn::{Generics, Ident};
 
-type FieldInfo = (Ident, Type, Requirement, Option<DefaultValue>, bool);
+/// Generates the setter of every field, together with the impls of the
+/// `Settable` traits bounding the setters of `forbidden_if` fields.
+pub fn generate_setter_methods(
+    field_infos: &[FieldInfo],
+    type_params: &[Option<Ident>],
+    private: &proc_macro2::TokenStream,
+    state_module: &Ident,
+    builder_name: &Ident,
+    generics: &Generics,
+) -> (Vec<proc_macro2::TokenStream>, Vec<proc_macro2::TokenStream>) {
+    let mut settable_impls = Vec::new();
+    let setters = field_infos.iter().enumerate().map(|(idx, field)| {
+        let field_ident = &field.ident;
+        let req = &field.requirement;
+        let into_flag = &field.into;
+        let generic_params = &generics.params;
+        // The type of the builder once the field is in `state`, and the path
+        // constructing it.
+        let builder_with_state = |state: &Ident| {
+            let new_types = type_params
+                .iter()
+                .enumerate()
+                .filter_map(|(i, tp)| {
+                    let tp = tp.as_ref()?;
+                    if i == idx {
+                        Some(quote! { #state_module::#field_ident::#state })
+                    } else {
+                        Some(quote! { #tp })
+                    }
+                })
+                .collect::<Vec<_>>();
+            if generic_params.is_empty() {
+                (
+                    quote! { #builder_name < #( #new_types ),* > },
+                    quote! { #builder_name::< #( #new_types ),* > },
+                )
+            } else {
+                (
+                    quote! { #builder_name < #generic_params, #( #new_types ),* > },
+                    quote! { #builder_name::< #generic_params, #( #new_types ),* > },
+                )
+            }
+        };
+        let states = field.states();
+        let ty = &field.ty;
 
-pub fn generate_setter_methods<'a>(
-    field_infos: &'a [FieldInfo],
-    type_params: &'a [Ident],
-    builder_name: &'a Ident,
-    generics: &'a Generics,
-    span: proc_macro2::Span,
-) -> impl Iterator<Item = proc_macro2::TokenStream> + 'a {
-    field_infos.iter().enumerate().map(
-        move |(idx, (field_ident, field_ty, req, _default, into_flag))| {
-            let mut new_types = type_params.to_vec();
-            new_types[idx] = Ident::new("_TypesafeBuilderFilled", span);
+        // A flag is turned on and off, and a variant of a selector chosen, by
+        // setters without arguments, each moving the field to the state
+        // conditions see it in.
+        let argumentless_setters = match req {
+            Requirement::Flag => vec![
+                (
+                    field.setter.clone(),
+                    &states[1],
+                    quote! { <#ty as #private::From<bool>>::from(true) },
+                ),
+                (
+                    field.disable_setter(),
+                    &states[0],
+                    quote! { <#ty as #private::From<bool>>::from(false) },
+                ),
+            ],
+            Requirement::Selector(_) => {
+                let enum_path = selector_enum(ty).expect("selectors are validated");
+                field
+                    .selector_setters()
+                    .into_iter()
+                    .zip(&states[1..])
+                    .map(|((variant, setter), state)| {
+                        (
+                            setter,
+                            state,
+                            quote! { #private::Some(#enum_path::#variant) },
+                        )
+                    })
+                    .collect()
+            }
+            _ => Vec::new(),
+        };
+        if !argumentless_setters.is_empty() {
+            return argumentless_setters
+                .into_iter()
+                .map(|(function_name, state, value)| {
+                    let (new_builder_ty, builder_constructor) = builder_with_state(state);
+                    let setters_assign =
+                        field_assignments(field_infos, type_params, private, field_ident, &value);
+                    quote! {
+                        pub fn #function_name(self) -> #new_builder_ty {
+                            #builder_constructor {
+                                #( #setters_assign, )*
+                                _typesafe_builder_target : self._typesafe_builder_target,
+                            }
+                        }
+                    }
+                })
+                .collect();
+        }
 
-            let generic_params = &generics.params;
-            let new_builder_ty = if generic_params.is_empty() {
-                quote! { #builder_name < #( #new_types ),* > }
-            } else {
-                quote! { #builder_name < #generic_params, #( #new_types ),* > }
-            };
+        let (new_builder_ty, builder_constructor) = builder_with_state(&states[1]);
 
-            let inner_ty = extract_arg_type(field_ty, req);
-            let arg_ty = if *into_flag {
-                quote! { impl Into<#inner_ty> }
-            } else {
-                inner_ty.clone()
-            };
-            let param_name = if *into_flag {
-                let name_str = field_ident.to_string();
-                let capitalized = format!(
-                    "{}{}",
-                    name_str.chars().next().unwrap().to_uppercase(),
-                    &name_str[1..]
-                );
-                Ident::new(&format!("Value{capitalized}"), field_ident.span())
-            } else {
-                Ident::new("VALUE", field_ident.span())
-            };
+        let inner_ty = extract_arg_type(field);
+        let arg_ty = if *into_flag {
+            quote! { impl #private::Into<#inner_ty> }
+        } else {
+            inner_ty.clone()
+        };
+        let param_name = if *into_flag {
+            let capitalized = to_pascal_case(field_ident);
+            Ident::new(&format!("Value{capitalized}"), field_ident.span())
+        } else {
+            Ident::new("VALUE", field_ident.span())
+        };
 
-            let setters_assign = field_infos.iter().map(|(fname, _ty, _, _default, _)| {
-                let phantom = Ident::new(&format!("_{fname}"), fname.span());
-                if fname == field_ident {
-                    let value_expr = if *into_flag {
-                        quote! { value.into() }
-                    } else {
-                        quote! { value }
-                    };
-                    match req {
-                        Requirement::Optional => quote! {
-                            #fname : Some(#value_expr),
-                            #phantom : std::marker::PhantomData
-                        },
-                        Requirement::Conditional(_)
-                        | Requirement::Always
-                        | Requirement::OptionalIf(_)
-                        | Requirement::Default => quote! {
-                            #fname : Some(#value_expr),
-                            #phantom : std::marker::PhantomData
-                        },
-                    }
-                } else {
-                    quote! { #fname : self.#fname, #phantom : self.#phantom }
-                }
-            });
+        let value_expr = if *into_flag {
+            quote! { value.into() }
+        } else {
+            quote! { value }
+        };
 
-            let function_name = Ident::new(&format!("with_{field_ident}"), field_ident.span());
-            let builder_constructor = if generic_params.is_empty() {
-                quote! { #builder_name::< #( #new_types ),* > }
-            } else {
-                quote! { #builder_name::< #generic_params, #( #new_types ),* > }
-            };
+        let setters_assign = field_assignments(
+            field_infos,
+            type_params,
+            private,
+            field_ident,
+            &quote! { #private::Some(#value_expr) },
+        );
+
+        // The setter of a `forbidden_if` field is only callable in the states
+        // where its condition does not hold.
+        let settable_bound = match req {
+            Requirement::ForbiddenIf(expr) => {
+                let (impls, bound) = state_constraint(
+                    field_infos,
+                    type_params,
+                    private,
+                    state_module,
+                    idx,
+                    "Settable",
+                    (Some(expr), |_, holds| !holds),
+                );
+                settable_impls.extend(impls);
+                Some(bound)
+            }
+            _ => None,
+        };
+
+        let function_name = &field.setter;
 
+        // Fields without a typestate parameter keep the builder type as is.
+        if type_params[idx].is_none() {
             if *into_flag {
-                quote! {
-                    pub fn #function_name<#param_name>(self, value: #param_name) -> #new_builder_ty
+                return quote! {
+                    pub fn #function_name<#param_name>(mut self, value: #param_name) -> Self
                     where
-                        #param_name: Into<#inner_ty>
+                        #param_name: #private::Into<#inner_ty>
                     {
-                        #builder_constructor {
-                            #( #setters_assign, )*
-                        }
+                        self.#field_ident = #private::Some(#value_expr);
+                        self
                     }
+                };
+            }
+            return quote! {
+                pub fn #function_name(mut self, value: #arg_ty) -> Self {
+                    self.#field_ident = #private::Some(#value_expr);
+                    self
                 }
-            } else {
-                quote! {
-                    pub fn #function_name(self, value: #arg_ty) -> #new_builder_ty {
-                        #builder_constructor {
-                            #( #setters_assign, )*
-                        }
+            };
+        }
+
+        if *into_flag {
+            quote! {
+                pub fn #function_name<#param_name>(self, value: #param_name) -> #new_builder_ty
+                where
+                    #param_name: #private::Into<#inner_ty>,
+                    #settable_bound
+                {
+                    #builder_constructor {
+                        #( #setters_assign, )*
+                        _typesafe_builder_target : self._typesafe_builder_target,
+                    }
+                }
+            }
+        } else {
+            quote! {
+                pub fn #function_name(self, value: #arg_ty) -> #new_builder_ty
+                where
+                    #settable_bound
+                {
+                    #builder_constructor {
+                        #( #setters_assign, )*
+                        _typesafe_builder_target : self._typesafe_builder_target,
                     }
                 }
             }
-        },
-    )
+        }
+    });
+    let setters = setters.collect();
+
+    (setters, settable_impls)
+}
+
+/// Initializers moving every field of the builder into a builder of another
+/// state, with `value` assigned to `field_ident`.
+fn field_assignments(
+    field_infos: &[FieldInfo],
+    type_params: &[Option<Ident>],
+    private: &proc_macro2::TokenStream,
+    field_ident: &Ident,
+    value: &proc_macro2::TokenStream,
+) -> Vec<proc_macro2::TokenStream> {
+    field_infos
+        .iter()
+        .zip(type_params.iter())
+        .map(|(other, tp)| {
+            let fname = &other.ident;
+            let phantom = Ident::new(&format!("_{fname}"), fname.span());
+            let phantom_assign = tp.as_ref().map(|_| {
+                if fname == field_ident {
+                    quote! { , #phantom : #private::PhantomData }
+                } else {
+                    quote! { , #phantom : self.#phantom }
+                }
+            });
+            if fname == field_ident {
+                quote! {
+                    #fname : #value
+                    #phantom_assign
+                }
+            } else {
+                quote! { #fname : self.#fname #phantom_assign }
+            }
+        })
+        .collect()
 }
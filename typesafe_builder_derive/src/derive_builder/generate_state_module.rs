@@ -0,0 +1,282 @@
+use super::{
+    BuildTarget, FieldInfo,
+    group_constraints::group_traits,
+    selector_enum,
+    validate_condition::{call_args, variant_comparison},
+};
+use crate::input::Requirement;
+use quote::{ToTokens, quote};
+use syn::{Expr, ExprBinary, ExprCall, ExprPath, ExprUnary, Ident, UnOp};
+
+/// Generates the module holding the `Set` / `Unset` state of every field that
+/// has a typestate parameter, e.g. `user_builder::name::Set`, the `Enabled` /
+/// `Disabled` state of a flag, or a state per variant of a selector.
+pub fn generate_state_module(
+    field_infos: &[FieldInfo],
+    type_params: &[Option<Ident>],
+    state_module: &Ident,
+    builder_name: &Ident,
+    target: &BuildTarget,
+) -> proc_macro2::TokenStream {
+    let target_name = target.display_name.as_str();
+    let field_modules = field_infos
+        .iter()
+        .zip(type_params.iter())
+        .filter(|(_, tp)| tp.is_some())
+        .map(|(field, _)| {
+            let ident = &field.ident;
+            let display_name = field.display_name();
+            let states = field.states().into_iter().enumerate().map(|(idx, state)| {
+                let doc = match (&field.requirement, idx) {
+                    (Requirement::Flag, 0) => format!(
+                        "State of a `{builder_name}` whose `{display_name}` flag is disabled."
+                    ),
+                    (Requirement::Flag, _) => format!(
+                        "State of a `{builder_name}` whose `{display_name}` flag is enabled."
+                    ),
+                    (_, 0) => format!(
+                        "State of a `{builder_name}` whose `{display_name}` field has not been set."
+                    ),
+                    (Requirement::Selector(_), _) => {
+                        let enum_path = selector_enum(&field.ty)
+                            .expect("selectors are validated")
+                            .to_token_stream()
+                            .to_string()
+                            .replace(' ', "");
+                        format!(
+                            "State of a `{builder_name}` whose `{display_name}` field is `{enum_path}::{state}`."
+                        )
+                    }
+                    _ => format!(
+                        "State of a `{builder_name}` whose `{display_name}` field has been set."
+                    ),
+                };
+                quote! {
+                    #[doc = #doc]
+                    pub struct #state;
+                }
+            });
+
+            let requirement_trait = match &field.requirement {
+                Requirement::Always | Requirement::Conditional(_) | Requirement::OptionalIf(_) => {
+                    let (message, label, note) = requirement_diagnostic(field_infos, target, field);
+                    Some(quote! {
+                        #[doc(hidden)]
+                        #[diagnostic::on_unimplemented(message = #message, label = #label, note = #note)]
+                        pub trait Requirement {}
+                    })
+                }
+                Requirement::ForbiddenIf(expr) => {
+                    let (message, label, note) = conflict_diagnostic(field_infos, target, field, expr);
+                    let (setter_message, setter_label, setter_note) =
+                        forbidden_setter_diagnostic(field_infos, target_name, field, expr);
+                    Some(quote! {
+                        #[doc(hidden)]
+                        #[diagnostic::on_unimplemented(message = #message, label = #label, note = #note)]
+                        pub trait Requirement {}
+
+                        #[doc(hidden)]
+                        #[diagnostic::on_unimplemented(message = #setter_message, label = #setter_label, note = #setter_note)]
+                        pub trait Settable {}
+                    })
+                }
+                Requirement::Selector(_) if field.is_required_selector() => {
+                    let (message, label, note) = requirement_diagnostic(field_infos, target, field);
+                    Some(quote! {
+                        #[doc(hidden)]
+                        #[diagnostic::on_unimplemented(message = #message, label = #label, note = #note)]
+                        pub trait Requirement {}
+                    })
+                }
+                Requirement::Optional
+                | Requirement::Default
+                | Requirement::Flag
+                | Requirement::Selector(_) => None,
+            };
+            let conflict_trait = field.conflicts_with.as_ref().map(|expr| {
+                let (message, label, note) = conflict_diagnostic(field_infos, target, field, expr);
+                quote! {
+                    #[doc(hidden)]
+                    #[diagnostic::on_unimplemented(message = #message, label = #label, note = #note)]
+                    pub trait Conflict {}
+                }
+            });
+
+            quote! {
+                pub mod #ident {
+                    #( #states )*
+
+                    #requirement_trait
+
+                    #conflict_trait
+                }
+            }
+        });
+
+    let group_traits = group_traits(field_infos, target);
+
+    let module_doc = format!("Field states of `{builder_name}`.");
+    quote! {
+        #[doc = #module_doc]
+        pub mod #state_module {
+            #( #field_modules )*
+
+            #( #group_traits )*
+        }
+    }
+}
+
+/// Builds the `message`, `label` and `note` reported when the requirement of
+/// `field` is not satisfied by the state the finisher is called in.
+fn requirement_diagnostic(
+    field_infos: &[FieldInfo],
+    target: &BuildTarget,
+    field: &FieldInfo,
+) -> (String, String, String) {
+    let name = field.display_name();
+    let action = target.action();
+    let message = match &field.requirement {
+        Requirement::Always | Requirement::Selector(_) => {
+            format!("cannot {action}: required field `{name}` is not set")
+        }
+        Requirement::Conditional(expr) => format!(
+            "cannot {action}: field `{name}` is required because {}",
+            describe_condition(field_infos, expr, true)
+        ),
+        Requirement::OptionalIf(expr) => format!(
+            "cannot {action}: field `{name}` is required because {}",
+            describe_condition(field_infos, expr, false)
+        ),
+        Requirement::Optional
+        | Requirement::Default
+        | Requirement::ForbiddenIf(_)
+        | Requirement::Flag => {
+            unreachable!()
+        }
+    };
+    let label = format!("missing `{name}`");
+    let note = format!(
+        "call {} before `.{}()`",
+        field.setter_call(),
+        target.finisher()
+    );
+
+    (message, label, note)
+}
+
+/// Builds the `message`, `label` and `note` reported when `field` is set
+/// while its `conflicts_with` or `forbidden_if` condition holds.
+fn conflict_diagnostic(
+    field_infos: &[FieldInfo],
+    target: &BuildTarget,
+    field: &FieldInfo,
+    expr: &Expr,
+) -> (String, String, String) {
+    let name = field.display_name();
+    let action = target.action();
+    let message = format!(
+        "cannot {action}: field `{name}` cannot be set because {}",
+        describe_condition(field_infos, expr, true)
+    );
+    let label = format!("conflicting `{name}`");
+    let note = format!(
+        "remove the call to {} or to the setters it conflicts with",
+        field.setter_call()
+    );
+
+    (message, label, note)
+}
+
+/// Builds the `message`, `label` and `note` reported when the setter of a
+/// `forbidden_if` field is called while its condition holds.
+fn forbidden_setter_diagnostic(
+    field_infos: &[FieldInfo],
+    target_name: &str,
+    field: &FieldInfo,
+    expr: &Expr,
+) -> (String, String, String) {
+    let name = field.display_name();
+    let message = format!(
+        "cannot set field `{name}` of `{target_name}` because {}",
+        describe_condition(field_infos, expr, true)
+    );
+    let label = format!("`{name}` cannot be set here");
+    let note = format!(
+        "`{name}` can only be set while {}",
+        describe_condition(field_infos, expr, false)
+    );
+
+    (message, label, note)
+}
+
+/// Describes why the condition makes its field required. `holds` is `true`
+/// for `required_if`, where the condition holding is the reason, and `false`
+/// for `optional_if`, where the condition not holding is.
+fn describe_condition(field_infos: &[FieldInfo], expr: &Expr, holds: bool) -> String {
+    match expr {
+        Expr::Path(ExprPath { path, .. }) => {
+            let field = path.to_token_stream().to_string();
+            let is_flag = field_infos
+                .iter()
+                .any(|info| info.ident == field && matches!(info.requirement, Requirement::Flag));
+            match (is_flag, holds) {
+                (false, true) => format!("`{field}` is set"),
+                (false, false) => format!("`{field}` is not set"),
+                (true, true) => format!("`{field}` is enabled"),
+                (true, false) => format!("`{field}` is disabled"),
+            }
+        }
+        Expr::Unary(ExprUnary {
+            op: UnOp::Not(_),
+            expr,
+            ..
+        }) if matches!(**expr, Expr::Path(_)) => describe_condition(field_infos, expr, !holds),
+        Expr::Paren(expr_paren) => describe_condition(field_infos, &expr_paren.expr, holds),
+        Expr::Binary(binary) if variant_comparison(binary).is_some() => {
+            let (field, equal, variant) = variant_comparison(binary).unwrap();
+            if equal == holds {
+                format!("`{field}` is `{variant}`")
+            } else {
+                format!("`{field}` is not `{variant}`")
+            }
+        }
+        _ => {
+            let condition = render_condition(expr);
+            if holds {
+                format!("`{condition}` holds")
+            } else {
+                format!("`{condition}` does not hold")
+            }
+        }
+    }
+}
+
+/// Renders a condition the way it is written in the attribute, turning the
+/// `implies` calls `->` is parsed into back into arrows.
+pub fn render_condition(expr: &Expr) -> String {
+    match expr {
+        Expr::Paren(expr_paren) => format!("({})", render_condition(&expr_paren.expr)),
+        Expr::Unary(ExprUnary {
+            op: UnOp::Not(_),
+            expr,
+            ..
+        }) => format!("!{}", render_condition(expr)),
+        Expr::Binary(ExprBinary {
+            left, op, right, ..
+        }) => format!(
+            "{} {} {}",
+            render_condition(left),
+            op.to_token_stream(),
+            render_condition(right)
+        ),
+        Expr::Call(ExprCall { func, args, .. }) => {
+            let args = args.iter().map(render_condition).collect::<Vec<_>>();
+            if call_args(expr, "implies").is_some() {
+                format!("{} -> {}", args[0], args[1])
+            } else {
+                format!("{}({})", func.to_token_stream(), args.join(", "))
+            }
+        }
+        _ => expr.to_token_stream().to_string(),
+    }
+}
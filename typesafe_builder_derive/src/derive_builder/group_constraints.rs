@@ -0,0 +1,146 @@
+use super::{BuildTarget, FieldInfo, to_pascal_case};
+use crate::input::{Group, GroupKind};
+use quote::{quote, quote_spanned};
+use syn::Ident;
+
+/// Checks that every group has a unique name and only refers to existing
+/// fields, each at most once.
+pub fn validate_groups(groups: &[Group], field_infos: &[FieldInfo]) -> Result<(), darling::Error> {
+    let mut errors = darling::Error::accumulator();
+    for (idx, group) in groups.iter().enumerate() {
+        if groups[..idx].iter().any(|other| other.name == group.name) {
+            errors.push(
+                darling::Error::custom(format!(
+                    "Group '{}' is declared more than once",
+                    group.name
+                ))
+                .with_span(&group.name),
+            );
+        }
+        for (position, field) in group.fields.iter().enumerate() {
+            if !field_infos.iter().any(|info| info.ident == *field) {
+                errors.push(
+                    darling::Error::custom(format!(
+                        "Field '{field}' used in group '{}' does not exist",
+                        group.name
+                    ))
+                    .with_span(field),
+                );
+            } else if group.fields[..position].contains(field) {
+                errors.push(
+                    darling::Error::custom(format!(
+                        "Field '{field}' appears more than once in group '{}'",
+                        group.name
+                    ))
+                    .with_span(field),
+                );
+            }
+        }
+    }
+    errors.finish()
+}
+
+/// Indices in `field_infos` of the fields of `group`.
+pub fn group_members(group: &Group, field_infos: &[FieldInfo]) -> Vec<usize> {
+    group
+        .fields
+        .iter()
+        .filter_map(|field| field_infos.iter().position(|info| info.ident == *field))
+        .collect()
+}
+
+/// Name of the trait of the state module checking `group`, e.g.
+/// `SourceGroup`.
+pub fn group_trait(group: &Group) -> Ident {
+    Ident::new(
+        &format!("{}Group", to_pascal_case(&group.name)),
+        group.name.span(),
+    )
+}
+
+/// Implements the trait of every group for the allowed numbers of its fields
+/// set. Returns these impls and the bounds `build` places on the traits, on
+/// the number of fields set counted one field after the other up to `Many`.
+pub fn group_constraints(
+    groups: &[Group],
+    field_infos: &[FieldInfo],
+    type_params: &[Option<Ident>],
+    private: &proc_macro2::TokenStream,
+    state_module: &Ident,
+) -> (Vec<proc_macro2::TokenStream>, Vec<proc_macro2::TokenStream>) {
+    let logic = quote! { #private::logic };
+    let mut impls = Vec::new();
+    let mut bounds = Vec::new();
+    for group in groups {
+        let members = group_members(group, field_infos);
+        let trait_ident = group_trait(group);
+        let group_state_module = Ident::new(&state_module.to_string(), group.name.span());
+        let trait_path = quote_spanned! {group.name.span()=> #group_state_module::#trait_ident };
+
+        let allowed: &[&str] = match group.kind {
+            GroupKind::Exactly => &["One"],
+            GroupKind::AtLeast => &["One", "Many"],
+            GroupKind::AtMost => &["Zero", "One"],
+        };
+        impls.extend(allowed.iter().map(|tally| {
+            let tally = Ident::new(tally, group.name.span());
+            quote! { impl #trait_path for #logic::#tally {} }
+        }));
+
+        let params = members
+            .iter()
+            .map(|&i| {
+                type_params[i]
+                    .as_ref()
+                    .expect("group fields have a typestate parameter")
+            })
+            .collect::<Vec<_>>();
+        let count = params.iter().fold(quote! { #logic::Zero }, |count, param| {
+            quote! { <<#param as #logic::IsSet>::Output as #logic::Bool>::Tally<#count> }
+        });
+        bounds.push(quote! {
+            #( #params: #logic::IsSet, )*
+            #count: #trait_path
+        });
+    }
+    (impls, bounds)
+}
+
+/// Declares the trait of every group, reporting which fields must be set
+/// when `build` is called in a state violating it.
+pub fn group_traits(
+    field_infos: &[FieldInfo],
+    target: &BuildTarget,
+) -> Vec<proc_macro2::TokenStream> {
+    let action = target.action();
+    target
+        .groups
+        .iter()
+        .map(|group| {
+            let members = group_members(group, field_infos);
+            let quantifier = group.kind.attribute_name().replace('_', " ");
+            let names = members
+                .iter()
+                .map(|&i| format!("`{}`", field_infos[i].display_name()))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let setters = members
+                .iter()
+                .map(|&i| field_infos[i].setter_call())
+                .collect::<Vec<_>>()
+                .join(", ");
+            let message = format!("cannot {action}: {quantifier} of {names} must be set");
+            let label = format!("group `{}` is not satisfied", group.name);
+            let note = format!(
+                "group `{}` requires {quantifier} of {setters} to be called",
+                group.name
+            );
+            let trait_ident = group_trait(group);
+            quote! {
+                #[doc(hidden)]
+                #[diagnostic::on_unimplemented(message = #message, label = #label, note = #note)]
+                pub trait #trait_ident {}
+            }
+        })
+        .collect()
+}
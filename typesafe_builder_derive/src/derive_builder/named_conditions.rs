@@ -0,0 +1,150 @@
+use super::validate_condition::{validate_condition_fields, variant_comparison};
+use crate::input::NamedCondition;
+use syn::{Expr, ExprBinary, ExprCall, ExprParen, ExprPath, ExprUnary, Ident, token::Paren};
+
+/// Named conditions of a struct or variant, each with every reference to
+/// another named condition already expanded.
+pub struct NamedConditions {
+    names: Vec<String>,
+    conditions: Vec<Expr>,
+}
+
+impl NamedConditions {
+    /// Parses and validates `conditions`, rejecting names declared twice or
+    /// shared with a field, and conditions referring to themselves.
+    pub fn resolve(conditions: &[NamedCondition], field_names: &[String]) -> syn::Result<Self> {
+        let names = conditions
+            .iter()
+            .map(|condition| condition.name.to_string())
+            .collect::<Vec<_>>();
+        for (idx, condition) in conditions.iter().enumerate() {
+            if field_names.contains(&names[idx]) {
+                return Err(syn::Error::new_spanned(
+                    &condition.name,
+                    format!(
+                        "Condition '{}' has the same name as a field",
+                        condition.name
+                    ),
+                ));
+            }
+            if names[..idx].contains(&names[idx]) {
+                return Err(syn::Error::new_spanned(
+                    &condition.name,
+                    format!("Condition '{}' is declared more than once", condition.name),
+                ));
+            }
+        }
+
+        let parsed = conditions
+            .iter()
+            .map(|condition| {
+                condition
+                    .condition()
+                    .map_err(|err| syn::Error::new(err.span(), format!("Invalid condition: {err}")))
+            })
+            .collect::<syn::Result<Vec<_>>>()?;
+        let mut resolver = Resolver {
+            names: &names,
+            parsed: &parsed,
+            resolved: vec![None; parsed.len()],
+            expanding: Vec::new(),
+        };
+        for idx in 0..parsed.len() {
+            let condition = resolver.resolve(idx)?;
+            validate_condition_fields(&condition, field_names)?;
+        }
+
+        let conditions = resolver.resolved.into_iter().flatten().collect();
+        Ok(NamedConditions { names, conditions })
+    }
+
+    /// Replaces every reference to a named condition in `expr` with the
+    /// parenthesized condition.
+    pub fn expand(&self, expr: &Expr) -> Expr {
+        substitute(expr, &mut |ident| {
+            let idx = self.names.iter().position(|name| ident == name);
+            Ok(idx.map(|idx| self.conditions[idx].clone()))
+        })
+        .expect("named conditions are resolved before expansion")
+    }
+}
+
+struct Resolver<'a> {
+    names: &'a [String],
+    parsed: &'a [Expr],
+    resolved: Vec<Option<Expr>>,
+    /// Indices of the conditions being expanded, outermost first.
+    expanding: Vec<usize>,
+}
+
+impl Resolver<'_> {
+    fn resolve(&mut self, idx: usize) -> syn::Result<Expr> {
+        if let Some(resolved) = &self.resolved[idx] {
+            return Ok(resolved.clone());
+        }
+        self.expanding.push(idx);
+        let parsed = self.parsed[idx].clone();
+        let resolved = substitute(&parsed, &mut |ident| {
+            let Some(referenced) = self.names.iter().position(|name| ident == name) else {
+                return Ok(None);
+            };
+            if let Some(start) = self.expanding.iter().position(|&i| i == referenced) {
+                let cycle = self.expanding[start..]
+                    .iter()
+                    .chain([&referenced])
+                    .map(|&i| self.names[i].as_str())
+                    .collect::<Vec<_>>()
+                    .join(" -> ");
+                return Err(syn::Error::new_spanned(
+                    ident,
+                    format!(
+                        "Condition '{}' refers to itself: {cycle}",
+                        self.names[referenced]
+                    ),
+                ));
+            }
+            self.resolve(referenced).map(Some)
+        })?;
+        self.expanding.pop();
+        self.resolved[idx] = Some(resolved.clone());
+        Ok(resolved)
+    }
+}
+
+/// Rebuilds `expr`, replacing each name for which `lookup` returns a
+/// condition with that condition in parentheses.
+fn substitute(
+    expr: &Expr,
+    lookup: &mut dyn FnMut(&Ident) -> syn::Result<Option<Expr>>,
+) -> syn::Result<Expr> {
+    let mut expr = expr.clone();
+    match &mut expr {
+        Expr::Path(ExprPath { path, qself, .. }) => {
+            if let Some(ident) = path.get_ident().filter(|_| qself.is_none())
+                && let Some(condition) = lookup(ident)?
+            {
+                return Ok(Expr::Paren(ExprParen {
+                    attrs: Vec::new(),
+                    paren_token: Paren(ident.span()),
+                    expr: Box::new(condition),
+                }));
+            }
+        }
+        Expr::Paren(ExprParen { expr, .. }) | Expr::Unary(ExprUnary { expr, .. }) => {
+            **expr = substitute(expr, lookup)?;
+        }
+        // The variant a selector is compared with is never a condition.
+        Expr::Binary(binary) if variant_comparison(binary).is_some() => {}
+        Expr::Binary(ExprBinary { left, right, .. }) => {
+            **left = substitute(left, lookup)?;
+            **right = substitute(right, lookup)?;
+        }
+        Expr::Call(ExprCall { args, .. }) => {
+            for arg in args.iter_mut() {
+                *arg = substitute(arg, lookup)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(expr)
+}
@@ -0,0 +1,332 @@
+use super::{
+    FieldInfo,
+    validate_condition::{call_args, count_limit, variant_comparison},
+};
+use crate::input::Requirement;
+use quote::{quote, quote_spanned};
+use std::collections::HashMap;
+use syn::{BinOp, Expr, ExprBinary, ExprPath, ExprUnary, Ident, UnOp};
+
+/// Condition of a constraint, and whether a state of the constrained field is
+/// allowed given whether it is filled and whether the condition holds.
+pub type Constraint<'a> = (Option<&'a Expr>, fn(bool, bool) -> bool);
+
+/// Constraints `build` places on `field`, each with the name of the trait of
+/// the state module checking it: its requirement, then its conflict.
+pub fn build_constraints(field: &FieldInfo) -> Vec<(&'static str, Constraint<'_>)> {
+    let requirement: Option<Constraint> = match &field.requirement {
+        Requirement::Always => Some((None, |filled, _| filled)),
+        Requirement::Conditional(expr) => Some((Some(expr), |filled, holds| filled || !holds)),
+        Requirement::OptionalIf(expr) => Some((Some(expr), |filled, holds| filled || holds)),
+        Requirement::ForbiddenIf(expr) => Some((Some(expr), |filled, holds| !(filled && holds))),
+        Requirement::Selector(_) if field.is_required_selector() => {
+            Some((None, |filled, _| filled))
+        }
+        Requirement::Optional
+        | Requirement::Default
+        | Requirement::Flag
+        | Requirement::Selector(_) => None,
+    };
+    let conflict: Option<Constraint> = field
+        .conflicts_with
+        .as_ref()
+        .map(|expr| (Some(expr), (|filled, holds| !(filled && holds)) as _));
+
+    [("Requirement", requirement), ("Conflict", conflict)]
+        .into_iter()
+        .filter_map(|(trait_name, constraint)| Some((trait_name, constraint?)))
+        .collect()
+}
+
+/// Implements the `trait_name` trait of the state module of
+/// `field_infos[idx]` for the pairs of a state of the field and of whether
+/// the condition of `constraint` holds that it allows, or for the allowed
+/// states alone without a condition. Returns these impls and the bounds
+/// requiring the trait, placed on `build` or on a setter.
+pub fn state_constraint(
+    field_infos: &[FieldInfo],
+    type_params: &[Option<Ident>],
+    private: &proc_macro2::TokenStream,
+    state_module: &Ident,
+    idx: usize,
+    trait_name: &str,
+    (condition, allowed): Constraint,
+) -> (Vec<proc_macro2::TokenStream>, proc_macro2::TokenStream) {
+    let ident = &field_infos[idx].ident;
+    let param = &type_params[idx];
+    let field_state_module = Ident::new(&state_module.to_string(), ident.span());
+    let trait_ident = Ident::new(trait_name, ident.span());
+    let trait_path = quote_spanned! {ident.span()=> #field_state_module::#ident::#trait_ident };
+    let states = field_infos[idx].states();
+    let states = states
+        .iter()
+        .enumerate()
+        .map(|(state_idx, state)| (state_idx != 0, quote! { #state_module::#ident::#state }));
+
+    let Some(expr) = condition else {
+        let impls = states
+            .filter(|(filled, _)| allowed(*filled, true))
+            .map(|(_, state)| quote! { impl #trait_path for ( #state, ) {} })
+            .collect();
+        return (impls, quote! { ( #param, ): #trait_path });
+    };
+
+    let logic = quote! { #private::logic };
+    let mut impls = Vec::new();
+    for (filled, state) in states {
+        for holds in [false, true]
+            .into_iter()
+            .filter(|&holds| allowed(filled, holds))
+        {
+            let holds = truth(&logic, holds);
+            impls.push(quote! { impl #trait_path for ( #state, #holds ) {} });
+        }
+    }
+    let mut bounds = Vec::new();
+    let holds = condition_type(
+        expr,
+        field_infos,
+        type_params,
+        &logic,
+        state_module,
+        &mut bounds,
+    );
+    let bound = quote! {
+        #( #bounds, )*
+        ( #param, #holds ): #trait_path
+    };
+    (impls, bound)
+}
+
+/// Implements, for every state of every field with a typestate parameter,
+/// whether the field is set in it, and for a selector whether each of its
+/// variants is chosen. Conditions are evaluated from these.
+pub fn state_values(
+    field_infos: &[FieldInfo],
+    type_params: &[Option<Ident>],
+    private: &proc_macro2::TokenStream,
+    state_module: &Ident,
+) -> Vec<proc_macro2::TokenStream> {
+    let logic = quote! { #private::logic };
+    let mut impls = Vec::new();
+    for (field, _) in field_infos
+        .iter()
+        .zip(type_params)
+        .filter(|(_, tp)| tp.is_some())
+    {
+        let ident = &field.ident;
+        let variants = match &field.requirement {
+            Requirement::Selector(variants) => variants.as_slice(),
+            _ => &[],
+        };
+        for (idx, state) in field.states().iter().enumerate() {
+            let is_set = truth(&logic, idx != 0);
+            impls.push(quote! {
+                impl #logic::IsSet for #state_module::#ident::#state {
+                    type Output = #is_set;
+                }
+            });
+            for (variant_idx, variant) in variants.iter().enumerate() {
+                let is_chosen = truth(&logic, idx == variant_idx + 1);
+                impls.push(quote! {
+                    impl #logic::IsChosen<#state_module::#ident::#variant> for #state_module::#ident::#state {
+                        type Output = #is_chosen;
+                    }
+                });
+            }
+        }
+    }
+    impls
+}
+
+/// The type `condition` evaluates to, `True` or `False`, from the states of
+/// the fields it refers to. The bounds the evaluation needs are pushed onto
+/// `bounds`.
+fn condition_type(
+    condition: &Expr,
+    field_infos: &[FieldInfo],
+    type_params: &[Option<Ident>],
+    logic: &proc_macro2::TokenStream,
+    state_module: &Ident,
+    bounds: &mut Vec<proc_macro2::TokenStream>,
+) -> proc_macro2::TokenStream {
+    let recurse = |expr: &Expr, bounds: &mut Vec<proc_macro2::TokenStream>| {
+        condition_type(expr, field_infos, type_params, logic, state_module, bounds)
+    };
+    let param = |field: &Ident| {
+        field_infos
+            .iter()
+            .position(|info| info.ident == *field)
+            .and_then(|i| type_params[i].as_ref())
+            .expect("fields conditions refer to have a typestate parameter")
+    };
+    let require = |bounds: &mut Vec<proc_macro2::TokenStream>, bound: proc_macro2::TokenStream| {
+        if !bounds
+            .iter()
+            .any(|other| other.to_string() == bound.to_string())
+        {
+            bounds.push(bound);
+        }
+    };
+    match condition {
+        Expr::Path(ExprPath { path, .. }) => {
+            let param = param(&path.segments.last().unwrap().ident);
+            require(bounds, quote! { #param: #logic::IsSet });
+            quote! { <#param as #logic::IsSet>::Output }
+        }
+        Expr::Paren(expr_paren) => recurse(&expr_paren.expr, bounds),
+        Expr::Unary(ExprUnary {
+            op: UnOp::Not(_),
+            expr,
+            ..
+        }) => {
+            let value = recurse(expr, bounds);
+            quote! { <#value as #logic::Bool>::Not }
+        }
+        Expr::Binary(binary) if variant_comparison(binary).is_some() => {
+            let (field, equal, variant) = variant_comparison(binary).unwrap();
+            let param = param(field);
+            let variant = quote! { #state_module::#field::#variant };
+            require(bounds, quote! { #param: #logic::IsChosen<#variant> });
+            let chosen = quote! { <#param as #logic::IsChosen<#variant>>::Output };
+            match equal {
+                true => chosen,
+                false => quote! { <#chosen as #logic::Bool>::Not },
+            }
+        }
+        Expr::Binary(ExprBinary {
+            left, op, right, ..
+        }) if call_args(left, "count").is_some() => {
+            let args = call_args(left, "count").unwrap();
+            let count = count_type(&args, logic, bounds, &recurse);
+            let limit = limit_type(right, args.len(), logic);
+            match op {
+                BinOp::Eq(_) => quote! { <#count as #logic::Count>::Eq<#limit> },
+                BinOp::Ne(_) => {
+                    quote! { <<#count as #logic::Count>::Eq<#limit> as #logic::Bool>::Not }
+                }
+                BinOp::Lt(_) => quote! { <#count as #logic::Count>::Lt<#limit> },
+                BinOp::Le(_) => {
+                    quote! { <<#limit as #logic::Count>::Lt<#count> as #logic::Bool>::Not }
+                }
+                BinOp::Gt(_) => quote! { <#limit as #logic::Count>::Lt<#count> },
+                BinOp::Ge(_) => {
+                    quote! { <<#count as #logic::Count>::Lt<#limit> as #logic::Bool>::Not }
+                }
+                _ => unreachable!("conditions are validated before evaluation"),
+            }
+        }
+        Expr::Binary(ExprBinary {
+            left, op, right, ..
+        }) => {
+            let left = recurse(left, bounds);
+            let right = recurse(right, bounds);
+            match op {
+                BinOp::And(_) => quote! { <#left as #logic::Bool>::And<#right> },
+                BinOp::Or(_) => quote! { <#left as #logic::Bool>::Or<#right> },
+                BinOp::BitXor(_) => quote! { <#left as #logic::Bool>::Xor<#right> },
+                _ => unreachable!("conditions are validated before evaluation"),
+            }
+        }
+        Expr::Call(_) => {
+            if let Some(args) = call_args(condition, "implies") {
+                let premise = recurse(args[0], bounds);
+                let conclusion = recurse(args[1], bounds);
+                quote! { <<#premise as #logic::Bool>::Not as #logic::Bool>::Or<#conclusion> }
+            } else if let Some(args) = call_args(condition, "at_least") {
+                let count = count_type(&args[1..], logic, bounds, &recurse);
+                let limit = limit_type(args[0], args.len() - 1, logic);
+                quote! { <<#count as #logic::Count>::Lt<#limit> as #logic::Bool>::Not }
+            } else {
+                unreachable!("conditions are validated before evaluation")
+            }
+        }
+        _ => unreachable!("conditions are validated before evaluation"),
+    }
+}
+
+/// The count of `conditions` holding, adding them one by one.
+fn count_type(
+    conditions: &[&Expr],
+    logic: &proc_macro2::TokenStream,
+    bounds: &mut Vec<proc_macro2::TokenStream>,
+    condition_type: &impl Fn(&Expr, &mut Vec<proc_macro2::TokenStream>) -> proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    conditions
+        .iter()
+        .fold(quote! { #logic::Zero }, |count, condition| {
+            let value = condition_type(condition, bounds);
+            quote! { <#value as #logic::Bool>::Add<#count> }
+        })
+}
+
+/// The count a `count(..)` of `len` conditions is compared with, or the
+/// threshold of an `at_least`. Limits beyond `len + 1` compare the same way
+/// as `len + 1`, which keeps the type small.
+fn limit_type(
+    expr: &Expr,
+    len: usize,
+    logic: &proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    let limit = count_limit(expr).expect("conditions are validated before evaluation");
+    let limit = (limit as usize).min(len + 1);
+    (0..limit).fold(quote! { #logic::Zero }, |count, _| {
+        quote! { #logic::Succ<#count> }
+    })
+}
+
+/// `True` or `False`.
+fn truth(logic: &proc_macro2::TokenStream, value: bool) -> proc_macro2::TokenStream {
+    match value {
+        true => quote! { #logic::True },
+        false => quote! { #logic::False },
+    }
+}
+
+/// Every combination of states of `members`, each given as the index of the
+/// state of every member in its `FieldInfo::states`, the first member
+/// changing fastest.
+pub fn state_combinations(
+    field_infos: &[FieldInfo],
+    members: &[usize],
+) -> impl Iterator<Item = Vec<usize>> {
+    let radices = members
+        .iter()
+        .map(|&i| field_infos[i].states().len())
+        .collect::<Vec<_>>();
+    let count = radices.iter().product::<usize>();
+    (0..count).map(move |mut combination| {
+        radices
+            .iter()
+            .map(|&radix| {
+                let state = combination % radix;
+                combination /= radix;
+                state
+            })
+            .collect()
+    })
+}
+
+/// Variables conditions are evaluated with while `members` are in `states`:
+/// whether each member is set, and for a selector whether each of its
+/// variants is chosen, e.g. `transport::Tcp`.
+pub fn condition_vars(
+    field_infos: &[FieldInfo],
+    members: &[usize],
+    states: &[usize],
+) -> HashMap<String, bool> {
+    let mut vars = HashMap::new();
+    for (&i, &state) in members.iter().zip(states) {
+        let field = &field_infos[i];
+        vars.insert(field.ident.to_string(), state != 0);
+        if let Requirement::Selector(variants) = &field.requirement {
+            for (variant_idx, variant) in variants.iter().enumerate() {
+                vars.insert(
+                    format!("{}::{variant}", field.ident),
+                    state == variant_idx + 1,
+                );
+            }
+        }
+    }
+    vars
+}
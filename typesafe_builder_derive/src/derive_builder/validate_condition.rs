@@ -1,20 +1,242 @@
-use syn::{Expr, ExprBinary, ExprPath, ExprUnary};
+use quote::ToTokens;
+use syn::{BinOp, Expr, ExprBinary, ExprCall, ExprLit, ExprPath, ExprUnary, Ident, Lit, UnOp};
 
-pub fn validate_condition_fields(expr: &Expr, available_fields: &[String]) -> Result<(), String> {
+/// Checks that a condition only combines names of existing fields with `!`,
+/// `&&`, `||`, `^`, `->`, `at_least(n, ..)`, `count(..)` comparisons,
+/// comparisons of selectors with their variants and parentheses, pointing at
+/// the first construct that does not.
+pub fn validate_condition_fields(expr: &Expr, available_fields: &[String]) -> syn::Result<()> {
     match expr {
-        Expr::Path(ExprPath { path, .. }) => {
-            let key = path.segments.last().unwrap().ident.to_string();
+        Expr::Path(ExprPath { path, qself, .. }) => {
+            let Some(ident) = path.get_ident().filter(|_| qself.is_none()) else {
+                return Err(syn::Error::new_spanned(
+                    expr,
+                    format!(
+                        "conditions refer to fields by name, found path `{}`",
+                        path.to_token_stream().to_string().replace(' ', "")
+                    ),
+                ));
+            };
+            let key = ident.to_string();
             if !available_fields.contains(&key) {
-                return Err(format!("Field '{key}' used in condition does not exist"));
+                return Err(syn::Error::new_spanned(
+                    ident,
+                    format!("Field '{key}' used in condition does not exist"),
+                ));
             }
             Ok(())
         }
+        Expr::Field(field) => Err(syn::Error::new_spanned(
+            expr,
+            format!(
+                "conditions refer to fields by name, found `{}`, write `{}` instead",
+                expr.to_token_stream().to_string().replace(' ', ""),
+                field.member.to_token_stream()
+            ),
+        )),
         Expr::Paren(expr_paren) => validate_condition_fields(&expr_paren.expr, available_fields),
-        Expr::Unary(ExprUnary { expr, .. }) => validate_condition_fields(expr, available_fields),
-        Expr::Binary(ExprBinary { left, right, .. }) => {
+        Expr::Unary(ExprUnary {
+            op: UnOp::Not(_),
+            expr,
+            ..
+        }) => validate_condition_fields(expr, available_fields),
+        Expr::Unary(ExprUnary { op, .. }) => Err(unsupported_operator(op)),
+        Expr::Binary(ExprBinary {
+            left,
+            op: BinOp::And(_) | BinOp::Or(_) | BinOp::BitXor(_),
+            right,
+            ..
+        }) => {
             validate_condition_fields(left, available_fields)?;
             validate_condition_fields(right, available_fields)
         }
-        _ => Ok(()),
+        Expr::Binary(binary) if variant_comparison(binary).is_some() => {
+            let (field, _, variant) = variant_comparison(binary).unwrap();
+            validate_variant_comparison(field, variant, available_fields)
+        }
+        Expr::Binary(ExprBinary {
+            left,
+            op:
+                op @ (BinOp::Eq(_)
+                | BinOp::Ne(_)
+                | BinOp::Lt(_)
+                | BinOp::Le(_)
+                | BinOp::Gt(_)
+                | BinOp::Ge(_)),
+            right,
+            ..
+        }) => {
+            let Some(args) = call_args(left, "count") else {
+                let message = if matches!(op, BinOp::Eq(_) | BinOp::Ne(_)) {
+                    format!(
+                        "`{}` can only compare `count(..)` with an integer, e.g. `count(a, b) == 1`, or a selector with one of its variants, e.g. `transport == Tcp`",
+                        op.to_token_stream()
+                    )
+                } else {
+                    format!(
+                        "`{}` can only compare `count(..)` with an integer, e.g. `count(a, b) == 1`",
+                        op.to_token_stream()
+                    )
+                };
+                return Err(syn::Error::new_spanned(op, message));
+            };
+            validate_arguments(left, &args, available_fields)?;
+            count_limit(right)?;
+            Ok(())
+        }
+        Expr::Binary(ExprBinary { op, .. }) => Err(unsupported_operator(op)),
+        Expr::Call(call) => validate_call(expr, call, available_fields),
+        _ => Err(syn::Error::new_spanned(
+            expr,
+            format!(
+                "unsupported expression `{}` in condition, expected a field name combined with `!`, `&&`, `||`, `^`, `->` and parentheses",
+                expr.to_token_stream()
+            ),
+        )),
+    }
+}
+
+fn validate_call(expr: &Expr, call: &ExprCall, available_fields: &[String]) -> syn::Result<()> {
+    let args = call.args.iter().collect::<Vec<_>>();
+    if call_args(expr, "implies").is_some() {
+        if args.len() != 2 {
+            return Err(syn::Error::new_spanned(
+                expr,
+                "`implies` takes a premise and a conclusion, e.g. `implies(a, b)` or `a -> b`",
+            ));
+        }
+        return validate_arguments(expr, &args, available_fields);
+    }
+    if call_args(expr, "at_least").is_some() {
+        let usage = "`at_least` takes a number followed by conditions, e.g. `at_least(2, a, b, c)`";
+        let Some((limit, conditions)) = args.split_first() else {
+            return Err(syn::Error::new_spanned(expr, usage));
+        };
+        if !matches!(limit, Expr::Lit(_)) {
+            return Err(syn::Error::new_spanned(expr, usage));
+        }
+        count_limit(limit)?;
+        return validate_arguments(expr, conditions, available_fields);
+    }
+    if call_args(expr, "count").is_some() {
+        return Err(syn::Error::new_spanned(
+            expr,
+            "`count(..)` must be compared with an integer, e.g. `count(a, b) == 1`",
+        ));
+    }
+    Err(syn::Error::new_spanned(
+        &call.func,
+        format!(
+            "unknown function `{}` in condition, expected `at_least`, `count` or `implies`",
+            call.func.to_token_stream()
+        ),
+    ))
+}
+
+/// The selector, whether it is compared with `==` rather than `!=`, and the
+/// variant of a comparison such as `transport == Tcp`.
+pub fn variant_comparison(binary: &ExprBinary) -> Option<(&Ident, bool, &Ident)> {
+    let equal = match binary.op {
+        BinOp::Eq(_) => true,
+        BinOp::Ne(_) => false,
+        _ => return None,
+    };
+    match (&*binary.left, &*binary.right) {
+        (
+            Expr::Path(ExprPath {
+                path: field,
+                qself: None,
+                ..
+            }),
+            Expr::Path(ExprPath {
+                path: variant,
+                qself: None,
+                ..
+            }),
+        ) => Some((field.get_ident()?, equal, variant.get_ident()?)),
+        _ => None,
+    }
+}
+
+/// Checks that `field` is a selector and `variant` one of its variants.
+fn validate_variant_comparison(
+    field: &Ident,
+    variant: &Ident,
+    available_fields: &[String],
+) -> syn::Result<()> {
+    if !available_fields.contains(&field.to_string()) {
+        return Err(syn::Error::new_spanned(
+            field,
+            format!("Field '{field}' used in condition does not exist"),
+        ));
     }
+    let prefix = format!("{field}::");
+    let variants = available_fields
+        .iter()
+        .filter_map(|name| name.strip_prefix(&prefix))
+        .map(|name| format!("`{name}`"))
+        .collect::<Vec<_>>();
+    if variants.is_empty() {
+        return Err(syn::Error::new_spanned(
+            field,
+            format!(
+                "`{field}` is not a selector, only fields marked with `#[builder(selector(...))]` can be compared with a variant"
+            ),
+        ));
+    }
+    if !available_fields.contains(&format!("{prefix}{variant}")) {
+        return Err(syn::Error::new_spanned(
+            variant,
+            format!(
+                "`{variant}` is not a variant of the selector `{field}`, expected {}",
+                variants.join(", ")
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Arguments of `expr` when it calls the condition function `name`.
+pub fn call_args<'a>(expr: &'a Expr, name: &str) -> Option<Vec<&'a Expr>> {
+    match expr {
+        Expr::Call(ExprCall { func, args, .. }) => match &**func {
+            Expr::Path(ExprPath {
+                path, qself: None, ..
+            }) if path.is_ident(name) => Some(args.iter().collect()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn validate_arguments(expr: &Expr, args: &[&Expr], available_fields: &[String]) -> syn::Result<()> {
+    if args.is_empty() {
+        return Err(syn::Error::new_spanned(
+            expr,
+            "expected at least one condition to count",
+        ));
+    }
+    args.iter()
+        .try_for_each(|arg| validate_condition_fields(arg, available_fields))
+}
+
+/// The integer a `count(..)` is compared with, or the threshold of
+/// `at_least`.
+pub fn count_limit(expr: &Expr) -> syn::Result<u32> {
+    match expr {
+        Expr::Lit(ExprLit {
+            lit: Lit::Int(int), ..
+        }) => int.base10_parse(),
+        _ => Err(syn::Error::new_spanned(expr, "expected an integer literal")),
+    }
+}
+
+fn unsupported_operator(op: &impl ToTokens) -> syn::Error {
+    syn::Error::new_spanned(
+        op,
+        format!(
+            "unsupported operator `{}` in condition, expected `!`, `&&`, `||`, `^` or `->`",
+            op.to_token_stream()
+        ),
+    )
 }
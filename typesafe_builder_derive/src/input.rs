@@ -1,10 +1,13 @@
-use darling::{FromDeriveInput, FromField, util::Flag};
-use syn::{Expr, Generics, Ident, Type};
+use darling::{FromDeriveInput, FromField, FromMeta, FromVariant, util::Flag};
+use proc_macro2::{Spacing, TokenStream, TokenTree};
+use syn::{Expr, Generics, Ident, LitStr, Path, Type, spanned::Spanned};
 
 #[derive(Debug, Clone)]
 pub enum DefaultValue {
     Bare,
     Expression(Expr),
+    /// Taken from the value built by the struct-level `default_from`.
+    Struct,
 }
 
 impl darling::FromMeta for DefaultValue {
@@ -14,23 +17,92 @@ impl darling::FromMeta for DefaultValue {
 
     fn from_value(value: &syn::Lit) -> darling::Result<Self> {
         match value {
-            syn::Lit::Str(s) => {
-                let expr: Expr = syn::parse_str(&s.value())?;
-                Ok(DefaultValue::Expression(expr))
-            }
+            syn::Lit::Str(s) => Ok(DefaultValue::Expression(s.parse()?)),
             _ => Err(darling::Error::unexpected_lit_type(value)),
         }
     }
 }
 
 #[derive(Debug, FromDeriveInput)]
-#[darling(attributes(builder), supports(struct_named))]
+#[darling(
+    attributes(builder),
+    supports(
+        struct_named,
+        struct_newtype,
+        struct_tuple,
+        enum_named,
+        enum_newtype,
+        enum_tuple,
+        enum_unit
+    ),
+    and_then = Self::check_options
+)]
 pub struct Input {
     ident: Ident,
     generics: Generics,
-    data: darling::ast::Data<(), InputField>,
+    data: darling::ast::Data<InputVariant, InputField>,
     #[darling(rename = "name")]
     builder_name: Option<String>,
+    #[darling(rename = "crate")]
+    crate_path: Option<Path>,
+    /// #[builder(validate_with = "...")]
+    validate_with: Option<Path>,
+    /// #[builder(error = "...")]
+    error: Option<Type>,
+    /// #[builder(group(...))]
+    #[darling(multiple, rename = "group")]
+    groups: Vec<Group>,
+    /// #[builder(condition(name = "..."))]
+    #[darling(multiple, rename = "condition")]
+    conditions: Vec<NamedConditions>,
+    /// #[builder(default_from = "...")]
+    default_from: Option<Path>,
+    /// #[builder(default_from_struct)]
+    #[darling(default)]
+    default_from_struct: Flag,
+    /// #[builder(fields(...))]
+    #[darling(default)]
+    fields: FieldsOptions,
+}
+
+/// Options applying to every field, given with `#[builder(fields(...))]`.
+#[derive(Debug, Default, FromMeta)]
+struct FieldsOptions {
+    default_requirement: Option<DefaultRequirement>,
+}
+
+/// Requirement of the fields without a requirement attribute, given with
+/// `#[builder(fields(default_requirement = "..."))]`.
+#[derive(Debug, Clone, Copy)]
+pub enum DefaultRequirement {
+    Required,
+    Optional,
+    /// `Option<T>` fields are optional, all others required.
+    Infer,
+}
+
+impl FromMeta for DefaultRequirement {
+    fn from_string(value: &str) -> darling::Result<Self> {
+        match value {
+            "required" => Ok(DefaultRequirement::Required),
+            "optional" => Ok(DefaultRequirement::Optional),
+            "infer" => Ok(DefaultRequirement::Infer),
+            _ => Err(darling::Error::custom(format!(
+                "unknown default requirement `{value}`, expected `required`, `optional` or `infer`"
+            ))),
+        }
+    }
+}
+
+/// Where the fields without a requirement attribute take their value from
+/// when left unset.
+#[derive(Debug, Clone)]
+pub enum DefaultSource {
+    /// `#[builder(default_from_struct)]`: the `Default` impl of the struct,
+    /// with the span of the attribute.
+    Default(proc_macro2::Span),
+    /// `#[builder(default_from = "...")]`: a function returning the struct.
+    Function(Path),
 }
 
 impl Input {
@@ -42,7 +114,7 @@ impl Input {
         &self.generics
     }
 
-    pub fn data(&self) -> darling::ast::Data<&(), &InputField> {
+    pub fn data(&self) -> darling::ast::Data<&InputVariant, &InputField> {
         self.data.as_ref()
     }
 
@@ -51,6 +123,270 @@ impl Input {
             .clone()
             .unwrap_or_else(|| format!("{}Builder", self.ident))
     }
+
+    /// Path to the `typesafe_builder` crate, `::typesafe_builder` unless
+    /// overridden with `#[builder(crate = "...")]`.
+    pub fn crate_path(&self) -> Path {
+        self.crate_path
+            .clone()
+            .unwrap_or_else(|| syn::parse_quote! { ::typesafe_builder })
+    }
+
+    /// Validator of the whole value given with `#[builder(validate_with = "...")]`,
+    /// together with the error type of `build` given with `#[builder(error = "...")]`.
+    pub fn validate_with(&self) -> Option<(&Path, &Type)> {
+        self.validate_with.as_ref().zip(self.error.as_ref())
+    }
+
+    pub fn groups(&self) -> &[Group] {
+        &self.groups
+    }
+
+    pub fn conditions(&self) -> impl Iterator<Item = &NamedCondition> {
+        self.conditions.iter().flat_map(|conditions| &conditions.0)
+    }
+
+    /// Requirement of the fields without a requirement attribute.
+    pub fn default_requirement(&self) -> Option<DefaultRequirement> {
+        self.fields.default_requirement
+    }
+
+    /// Struct-level source of the values of unannotated fields.
+    pub fn default_source(&self) -> Option<DefaultSource> {
+        match &self.default_from {
+            Some(path) => Some(DefaultSource::Function(path.clone())),
+            None if self.default_from_struct.is_present() => {
+                Some(DefaultSource::Default(self.default_from_struct.span()))
+            }
+            None => None,
+        }
+    }
+
+    fn check_options(self) -> darling::Result<Self> {
+        if let Some(default_from) = &self.default_from
+            && self.default_from_struct.is_present()
+        {
+            return Err(darling::Error::custom(
+                "`default_from` and `default_from_struct` cannot be used together",
+            )
+            .with_span(default_from));
+        }
+        if self.fields.default_requirement.is_some()
+            && (self.default_from.is_some() || self.default_from_struct.is_present())
+        {
+            let error = darling::Error::custom(
+                "`fields(default_requirement = \"...\")` cannot be used together with `default_from` or `default_from_struct`, which already decide the unannotated fields",
+            );
+            return Err(match &self.default_from {
+                Some(default_from) => error.with_span(default_from),
+                None => error.with_span(&self.default_from_struct.span()),
+            });
+        }
+        if self.data.is_enum() {
+            let error = darling::Error::custom("`default_from` is only supported on structs");
+            if let Some(default_from) = &self.default_from {
+                return Err(error.with_span(default_from));
+            }
+            if self.default_from_struct.is_present() {
+                return Err(error.with_span(&self.default_from_struct.span()));
+            }
+        }
+        match (&self.validate_with, &self.error) {
+            (Some(validate_with), None) => Err(darling::Error::custom(
+                "`validate_with` requires `error = \"...\"` naming the error type returned by `build`",
+            )
+            .with_span(validate_with)),
+            (None, Some(error)) => Err(darling::Error::custom(
+                "`error` can only be used together with `validate_with`",
+            )
+            .with_span(error)),
+            _ => Ok(self),
+        }
+    }
+}
+
+#[derive(Debug, FromVariant)]
+#[darling(attributes(builder))]
+pub struct InputVariant {
+    ident: Ident,
+    fields: darling::ast::Fields<InputField>,
+    #[darling(rename = "name")]
+    builder_name: Option<String>,
+    /// #[builder(group(...))]
+    #[darling(multiple, rename = "group")]
+    groups: Vec<Group>,
+    /// #[builder(condition(name = "..."))]
+    #[darling(multiple, rename = "condition")]
+    conditions: Vec<NamedConditions>,
+    /// #[builder(fields(...))]
+    #[darling(default, rename = "fields")]
+    fields_options: FieldsOptions,
+}
+
+impl InputVariant {
+    pub fn ident(&self) -> &Ident {
+        &self.ident
+    }
+
+    pub fn fields(&self) -> &darling::ast::Fields<InputField> {
+        &self.fields
+    }
+
+    /// Name of the variant builder, `{Enum}{Variant}Builder` unless overridden
+    /// with `#[builder(name = "...")]` on the variant.
+    pub fn builder_name(&self, enum_ident: &Ident) -> String {
+        self.builder_name
+            .clone()
+            .unwrap_or_else(|| format!("{enum_ident}{}Builder", self.ident))
+    }
+
+    pub fn groups(&self) -> &[Group] {
+        &self.groups
+    }
+
+    pub fn conditions(&self) -> impl Iterator<Item = &NamedCondition> {
+        self.conditions.iter().flat_map(|conditions| &conditions.0)
+    }
+
+    /// Requirement of the fields without a requirement attribute, declared
+    /// on the variant.
+    pub fn default_requirement(&self) -> Option<DefaultRequirement> {
+        self.fields_options.default_requirement
+    }
+}
+
+/// A condition declared once with `#[builder(condition(secure = "..."))]` and
+/// referred to by name from the conditions of fields.
+#[derive(Debug, Clone)]
+pub struct NamedCondition {
+    pub name: Ident,
+    condition: LitStr,
+}
+
+impl NamedCondition {
+    pub fn condition(&self) -> syn::Result<Expr> {
+        parse_condition(&self.condition)
+    }
+}
+
+/// The conditions of one `#[builder(condition(...))]` attribute.
+#[derive(Debug)]
+struct NamedConditions(Vec<NamedCondition>);
+
+impl FromMeta for NamedConditions {
+    fn from_list(items: &[darling::ast::NestedMeta]) -> darling::Result<Self> {
+        items
+            .iter()
+            .map(|item| match item {
+                darling::ast::NestedMeta::Meta(syn::Meta::NameValue(name_value)) => {
+                    let name = name_value.path.get_ident().cloned().ok_or_else(|| {
+                        darling::Error::custom("expected a condition name").with_span(item)
+                    })?;
+                    let condition = LitStr::from_expr(&name_value.value)?;
+                    Ok(NamedCondition { name, condition })
+                }
+                _ => Err(darling::Error::custom(
+                    "expected `name = \"...\"`, e.g. `condition(secure = \"use_auth || use_https\")`",
+                )
+                .with_span(item)),
+            })
+            .collect::<darling::Result<Vec<_>>>()
+            .map(NamedConditions)
+    }
+}
+
+/// A constraint on how many fields of a group are set, declared with
+/// `#[builder(group(name = "...", exactly_one = [a, b]))]`.
+#[derive(Debug, Clone)]
+pub struct Group {
+    pub name: Ident,
+    pub kind: GroupKind,
+    pub fields: Vec<Ident>,
+}
+
+/// Whether exactly, at least or at most one field of a group must be set.
+#[derive(Debug, Clone, Copy)]
+pub enum GroupKind {
+    Exactly,
+    AtLeast,
+    AtMost,
+}
+
+impl GroupKind {
+    /// Name of the kind as written in the attribute.
+    pub fn attribute_name(self) -> &'static str {
+        match self {
+            GroupKind::Exactly => "exactly_one",
+            GroupKind::AtLeast => "at_least_one",
+            GroupKind::AtMost => "at_most_one",
+        }
+    }
+
+    /// Whether `count` fields of the group may be set.
+    pub fn allows(self, count: u32) -> bool {
+        match self {
+            GroupKind::Exactly => count == 1,
+            GroupKind::AtLeast => count >= 1,
+            GroupKind::AtMost => count <= 1,
+        }
+    }
+}
+
+#[derive(FromMeta)]
+struct GroupOptions {
+    name: Ident,
+    exactly_one: Option<FieldList>,
+    at_least_one: Option<FieldList>,
+    at_most_one: Option<FieldList>,
+}
+
+impl FromMeta for Group {
+    fn from_list(items: &[darling::ast::NestedMeta]) -> darling::Result<Self> {
+        let options = GroupOptions::from_list(items)?;
+        let kinds = [
+            (GroupKind::Exactly, options.exactly_one),
+            (GroupKind::AtLeast, options.at_least_one),
+            (GroupKind::AtMost, options.at_most_one),
+        ]
+        .into_iter()
+        .filter_map(|(kind, fields)| Some((kind, fields?.0)))
+        .collect::<Vec<_>>();
+
+        match <[_; 1]>::try_from(kinds) {
+            Ok([(kind, fields)]) => Ok(Group {
+                name: options.name,
+                kind,
+                fields,
+            }),
+            Err(_) => Err(darling::Error::custom(
+                "group must specify exactly one of exactly_one, at_least_one, at_most_one",
+            )
+            .with_span(&options.name)),
+        }
+    }
+}
+
+/// Field names written as `[a, b, c]`.
+struct FieldList(Vec<Ident>);
+
+impl FromMeta for FieldList {
+    fn from_expr(expr: &Expr) -> darling::Result<Self> {
+        match expr {
+            Expr::Array(array) => array
+                .elems
+                .iter()
+                .map(|elem| match elem {
+                    Expr::Path(path) => path.path.get_ident().cloned().ok_or_else(|| {
+                        darling::Error::custom("expected a field name").with_span(elem)
+                    }),
+                    _ => Err(darling::Error::custom("expected a field name").with_span(elem)),
+                })
+                .collect::<darling::Result<Vec<_>>>()
+                .map(FieldList),
+            Expr::Group(group) => Self::from_expr(&group.expr),
+            _ => Err(darling::Error::unexpected_expr_type(expr)),
+        }
+    }
 }
 
 #[derive(Debug, FromField)]
@@ -69,19 +405,83 @@ pub struct InputField {
 
     /// #[builder(required_if"...")]
     #[darling(rename = "required_if", default)]
-    required_if: Option<String>,
+    required_if: Option<LitStr>,
 
     /// #[builder(optional_if"...")]
     #[darling(rename = "optional_if", default)]
-    optional_if: Option<String>,
+    optional_if: Option<LitStr>,
+
+    /// #[builder(forbidden_if = "...")]
+    #[darling(rename = "forbidden_if", default)]
+    forbidden_if: Option<LitStr>,
 
     /// #[builder(default = "...")]
     #[darling(rename = "default", default)]
     default: Option<DefaultValue>,
 
+    /// #[builder(default_if = "...")]
+    #[darling(default)]
+    default_if: Option<LitStr>,
+
+    /// #[builder(flag)]
+    #[darling(default)]
+    flag: Flag,
+
+    /// #[builder(selector(...))]
+    #[darling(default)]
+    selector: Option<VariantList>,
+
     /// #[builder(into)]
     #[darling(rename = "into", default)]
     into_flag: Flag,
+
+    /// #[builder(conflicts_with = "...")]
+    #[darling(default)]
+    conflicts_with: Option<LitStr>,
+
+    /// #[builder(validate = "...")]
+    #[darling(default)]
+    validate: Option<Path>,
+
+    /// #[builder(setter(name = "..."))]
+    #[darling(default)]
+    setter: SetterOptions,
+}
+
+/// Variants of the fieldless enum of a selector, written as
+/// `selector(Tcp, Unix)`.
+#[derive(Debug)]
+struct VariantList(Vec<Ident>);
+
+impl FromMeta for VariantList {
+    fn from_word() -> darling::Result<Self> {
+        Err(darling::Error::custom(
+            "`selector` must list the variants of the enum, e.g. `selector(Tcp, Unix)`",
+        ))
+    }
+
+    fn from_list(items: &[darling::ast::NestedMeta]) -> darling::Result<Self> {
+        if items.is_empty() {
+            return Self::from_word();
+        }
+        items
+            .iter()
+            .map(|item| match item {
+                darling::ast::NestedMeta::Meta(syn::Meta::Path(path)) => {
+                    path.get_ident().cloned().ok_or_else(|| {
+                        darling::Error::custom("expected a variant name").with_span(item)
+                    })
+                }
+                _ => Err(darling::Error::custom("expected a variant name").with_span(item)),
+            })
+            .collect::<darling::Result<Vec<_>>>()
+            .map(VariantList)
+    }
+}
+
+#[derive(Debug, Default, FromMeta)]
+pub struct SetterOptions {
+    name: Option<Ident>,
 }
 
 impl InputField {
@@ -93,49 +493,178 @@ impl InputField {
         &self.ty
     }
 
-    pub fn requirement(&self) -> syn::Result<Requirement> {
-        let attribute_count = self.optional_flag.is_present() as u8
+    /// Name of the setter given with `#[builder(setter(name = "..."))]`.
+    pub fn setter_name(&self) -> Option<&Ident> {
+        self.setter.name.as_ref()
+    }
+
+    fn span(&self) -> proc_macro2::Span {
+        self.ident
+            .as_ref()
+            .map_or_else(|| self.ty.span(), |ident| ident.span())
+    }
+
+    /// Whether the field has none of the attributes deciding its requirement,
+    /// nor a default.
+    pub fn is_unannotated(&self) -> bool {
+        self.requirement_attribute_count() == 0 && self.default.is_none()
+    }
+
+    fn requirement_attribute_count(&self) -> u8 {
+        self.optional_flag.is_present() as u8
             + self.required_flag.is_present() as u8
             + self.required_if.is_some() as u8
             + self.optional_if.is_some() as u8
-            + self.default.is_some() as u8;
+            + self.forbidden_if.is_some() as u8
+            + self.default_if.is_some() as u8
+            + self.flag.is_present() as u8
+            + self.selector.is_some() as u8
+    }
+
+    pub fn requirement(&self) -> syn::Result<Requirement> {
+        let attribute_count = self.requirement_attribute_count();
 
         if attribute_count > 1 {
             return Err(syn::Error::new(
-                self.ident.as_ref().unwrap().span(),
-                "Multiple builder attributes specified. Only one of required, optional, required_if, optional_if, default is allowed",
+                self.span(),
+                "Multiple builder attributes specified. Only one of required, optional, required_if, optional_if, forbidden_if, default_if, flag, selector is allowed",
             ));
         }
 
-        if self.default.is_some() {
-            Ok(Requirement::Default)
+        // A default fills in a conditionally required field whenever it is
+        // not required and left unset.
+        let conditional =
+            self.required_if.is_some() || self.optional_if.is_some() || self.default_if.is_some();
+        if self.default.is_some() && attribute_count > 0 && !conditional {
+            return Err(syn::Error::new(
+                self.span(),
+                "`default` can only be combined with required_if, optional_if or default_if",
+            ));
+        }
+
+        if let Some(default_if) = &self.default_if {
+            Ok(Requirement::OptionalIf(parse_condition(default_if)?))
         } else if let Some(opt_if) = &self.optional_if {
-            let expr_result: Result<Expr, _> = syn::parse_str(opt_if);
-            let expr: Expr = expr_result?;
-            Ok(Requirement::OptionalIf(expr))
+            Ok(Requirement::OptionalIf(parse_condition(opt_if)?))
+        } else if let Some(forbidden_if) = &self.forbidden_if {
+            Ok(Requirement::ForbiddenIf(parse_condition(forbidden_if)?))
         } else if let Some(req_if) = &self.required_if {
-            let expr_result: Result<Expr, _> = syn::parse_str(req_if);
-            let expr: Expr = expr_result?;
-            Ok(Requirement::Conditional(expr))
+            Ok(Requirement::Conditional(parse_condition(req_if)?))
+        } else if self.default.is_some() {
+            Ok(Requirement::Default)
         } else if self.required_flag.is_present() {
             Ok(Requirement::Always)
         } else if self.optional_flag.is_present() {
             Ok(Requirement::Optional)
+        } else if self.flag.is_present() {
+            Ok(Requirement::Flag)
+        } else if let Some(variants) = &self.selector {
+            Ok(Requirement::Selector(variants.0.clone()))
         } else {
             Err(syn::Error::new(
-                self.ident.as_ref().unwrap().span(),
-                "missing required field",
+                self.span(),
+                "missing required field: add `#[builder(required)]`, `#[builder(optional)]` or another requirement, or declare `#[builder(fields(default_requirement = \"...\"))]` on the type",
             ))
         }
     }
 
-    pub fn default(&self) -> Option<&DefaultValue> {
-        self.default.as_ref()
+    /// Default of the field: the one given with `default`, or
+    /// `Default::default()` for `default_if` without one.
+    pub fn default(&self) -> Option<DefaultValue> {
+        match (&self.default, &self.default_if) {
+            (Some(default), _) => Some(default.clone()),
+            (None, Some(_)) => Some(DefaultValue::Bare),
+            (None, None) => None,
+        }
     }
 
     pub fn into(&self) -> bool {
         self.into_flag.is_present()
     }
+
+    /// Condition given with `#[builder(conflicts_with = "...")]` under which
+    /// the field must not be set.
+    pub fn conflicts_with(&self) -> syn::Result<Option<Expr>> {
+        self.conflicts_with
+            .as_ref()
+            .map(parse_condition)
+            .transpose()
+    }
+
+    /// Variants listed with `#[builder(selector(...))]`.
+    pub fn selector_variants(&self) -> &[Ident] {
+        self.selector.as_ref().map_or(&[], |variants| &variants.0)
+    }
+
+    /// Validator given with `#[builder(validate = "...")]`.
+    pub fn validate(&self) -> Option<&Path> {
+        self.validate.as_ref()
+    }
+}
+
+/// Parses the condition of an attribute. `a -> b` is not a Rust expression,
+/// so implications are rewritten to `implies(a, b)` first.
+fn parse_condition(lit: &LitStr) -> syn::Result<Expr> {
+    let tokens = lit.parse::<TokenStream>()?;
+    syn::parse2(desugar_implications(tokens))
+}
+
+/// Rewrites `a -> b -> c` into `implies(a, implies(b, c))` at every nesting
+/// level, making `->` right associative with the lowest precedence within
+/// each comma-separated argument.
+fn desugar_implications(tokens: TokenStream) -> TokenStream {
+    let mut result = TokenStream::new();
+    let mut argument = Vec::new();
+    for token in tokens {
+        match token {
+            TokenTree::Punct(punct) if punct.as_char() == ',' => {
+                result.extend(desugar_argument(std::mem::take(&mut argument)));
+                result.extend([TokenTree::Punct(punct)]);
+            }
+            token => argument.push(token),
+        }
+    }
+    result.extend(desugar_argument(argument));
+    result
+}
+
+fn desugar_argument(tokens: Vec<TokenTree>) -> TokenStream {
+    let mut parts = vec![Vec::new()];
+    let mut arrows = Vec::new();
+    let mut iter = tokens.into_iter().peekable();
+    while let Some(token) = iter.next() {
+        match token {
+            TokenTree::Punct(punct)
+                if punct.as_char() == '-' && punct.spacing() == Spacing::Joint =>
+            {
+                match iter.peek() {
+                    Some(TokenTree::Punct(next)) if next.as_char() == '>' => {
+                        iter.next();
+                        arrows.push(punct.span());
+                        parts.push(Vec::new());
+                    }
+                    _ => parts.last_mut().unwrap().push(TokenTree::Punct(punct)),
+                }
+            }
+            TokenTree::Group(group) => {
+                let mut desugared = proc_macro2::Group::new(
+                    group.delimiter(),
+                    desugar_implications(group.stream()),
+                );
+                desugared.set_span(group.span());
+                parts.last_mut().unwrap().push(TokenTree::Group(desugared));
+            }
+            token => parts.last_mut().unwrap().push(token),
+        }
+    }
+
+    let mut parts = parts.into_iter().map(TokenStream::from_iter).rev();
+    let mut result = parts.next().unwrap_or_default();
+    for (premise, arrow) in parts.zip(arrows.into_iter().rev()) {
+        let implies = proc_macro2::Ident::new("implies", arrow);
+        result = quote::quote_spanned! {arrow=> #implies(#premise, #result) };
+    }
+    result
 }
 
 pub enum Requirement {
@@ -143,5 +672,9 @@ pub enum Requirement {
     Optional,
     Conditional(Expr),
     OptionalIf(Expr),
+    ForbiddenIf(Expr),
     Default,
+    Flag,
+    /// A field holding one of the listed variants of a fieldless enum.
+    Selector(Vec<Ident>),
 }
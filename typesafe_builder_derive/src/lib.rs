@@ -2,10 +2,10 @@ mod derive_builder;
 mod input;
 
 use darling::FromDeriveInput;
-use derive_builder::derive_builder_impl;
+use derive_builder::{derive_builder_impl, generate_fn_builder, generate_impl_builders};
 use input::Input;
 use proc_macro::TokenStream;
-use syn::{DeriveInput, parse_macro_input};
+use syn::{DeriveInput, Item, parse_macro_input};
 
 #[proc_macro_derive(Builder, attributes(builder))]
 pub fn derive_builder(tokens: TokenStream) -> TokenStream {
@@ -20,3 +20,26 @@ pub fn derive_builder(tokens: TokenStream) -> TokenStream {
         Err(e) => TokenStream::from(e.write_errors()),
     }
 }
+
+/// Generates a typestate builder for the arguments of a function, replacing
+/// it with a function of the same name that starts the builder. Arguments
+/// take the same `#[builder(...)]` attributes as fields, and the builder
+/// calls the original function from `call`. On an `impl` block, generates
+/// the builders of its functions marked with `#[builder]`.
+#[proc_macro_attribute]
+pub fn builder(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let item = parse_macro_input!(item as Item);
+
+    let expanded = match item {
+        Item::Fn(function) => generate_fn_builder(attr.into(), function),
+        Item::Impl(impl_block) => generate_impl_builders(attr.into(), impl_block),
+        item => Err(darling::Error::custom(
+            "`#[builder]` can only be used on functions and `impl` blocks",
+        )
+        .with_span(&item)),
+    };
+    match expanded {
+        Ok(expanded) => TokenStream::from(expanded),
+        Err(e) => TokenStream::from(e.write_errors()),
+    }
+}
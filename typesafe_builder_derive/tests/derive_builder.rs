@@ -1,8 +1,6 @@
-use typesafe_builder_derive::Builder;
+#![allow(clippy::bool_assert_comparison)]
 
-struct _TypesafeBuilderEmpty;
-
-struct _TypesafeBuilderFilled;
+use typesafe_builder_derive::{Builder, builder};
 
 #[test]
 fn required_field_success() {
@@ -192,6 +190,29 @@ fn generic_struct_where_clause_success() {
     assert_eq!(container.value, "hello");
 }
 
+#[test]
+fn field_type_named_like_its_state_success() {
+    #[derive(Clone, Debug, PartialEq)]
+    struct AppState {
+        name: String,
+    }
+
+    #[derive(Builder)]
+    struct Server {
+        #[builder(required)]
+        app: AppState,
+        #[builder(optional)]
+        port: Option<u16>,
+    }
+
+    let app = AppState {
+        name: "api".to_string(),
+    };
+    let server = ServerBuilder::new().with_app(app.clone()).build();
+    assert_eq!(server.app, app);
+    assert_eq!(server.port, None);
+}
+
 #[test]
 fn custom_builder_name_success() {
     #[derive(Builder, PartialEq)]
@@ -333,3 +354,1956 @@ fn bare_default_with_custom_types() {
     let config = ConfigBuilder::new().build();
     assert_eq!(config.custom, CustomType::default());
 }
+
+#[test]
+fn many_fields_success() {
+    #[derive(Builder, PartialEq)]
+    struct Wide {
+        #[builder(required)]
+        id: u32,
+        #[builder(optional)]
+        field_0: Option<u8>,
+        #[builder(optional)]
+        field_1: Option<u8>,
+        #[builder(optional)]
+        field_2: Option<u8>,
+        #[builder(optional)]
+        field_3: Option<u8>,
+        #[builder(optional)]
+        field_4: Option<u8>,
+        #[builder(optional)]
+        field_5: Option<u8>,
+        #[builder(optional)]
+        field_6: Option<u8>,
+        #[builder(optional)]
+        field_7: Option<u8>,
+        #[builder(optional)]
+        field_8: Option<u8>,
+        #[builder(optional)]
+        field_9: Option<u8>,
+        #[builder(optional)]
+        field_10: Option<u8>,
+        #[builder(optional)]
+        field_11: Option<u8>,
+        #[builder(optional)]
+        field_12: Option<u8>,
+        #[builder(optional)]
+        field_13: Option<u8>,
+        #[builder(optional)]
+        field_14: Option<u8>,
+        #[builder(optional)]
+        field_15: Option<u8>,
+        #[builder(optional)]
+        field_16: Option<u8>,
+        #[builder(optional)]
+        field_17: Option<u8>,
+        #[builder(optional)]
+        field_18: Option<u8>,
+        #[builder(optional)]
+        field_19: Option<u8>,
+        #[builder(optional)]
+        field_20: Option<u8>,
+        #[builder(optional)]
+        field_21: Option<u8>,
+        #[builder(optional)]
+        field_22: Option<u8>,
+        #[builder(optional)]
+        field_23: Option<u8>,
+        #[builder(optional)]
+        field_24: Option<u8>,
+        #[builder(optional)]
+        field_25: Option<u8>,
+        #[builder(optional)]
+        field_26: Option<u8>,
+        #[builder(optional)]
+        field_27: Option<u8>,
+        #[builder(optional)]
+        field_28: Option<u8>,
+        #[builder(optional)]
+        field_29: Option<u8>,
+        #[builder(optional)]
+        field_30: Option<u8>,
+        #[builder(optional)]
+        field_31: Option<u8>,
+        #[builder(optional)]
+        field_32: Option<u8>,
+        #[builder(optional)]
+        field_33: Option<u8>,
+        #[builder(optional)]
+        field_34: Option<u8>,
+        #[builder(optional)]
+        field_35: Option<u8>,
+        #[builder(optional)]
+        field_36: Option<u8>,
+        #[builder(optional)]
+        field_37: Option<u8>,
+        #[builder(optional)]
+        field_38: Option<u8>,
+        #[builder(optional)]
+        field_39: Option<u8>,
+        #[builder(required_if = "field_0 && field_39")]
+        extra: Option<String>,
+    }
+
+    let wide = WideBuilder::new().with_id(1).with_field_39(39).build();
+    assert_eq!(wide.id, 1);
+    assert_eq!(wide.field_0, None);
+    assert_eq!(wide.field_39, Some(39));
+    assert_eq!(wide.extra, None);
+
+    let wide = WideBuilder::new()
+        .with_id(1)
+        .with_field_0(0)
+        .with_field_39(39)
+        .with_extra("extra".to_string())
+        .build();
+    assert_eq!(wide.extra, Some("extra".to_string()));
+}
+
+#[test]
+fn unconstrained_fields_have_no_type_param() {
+    #[derive(Builder, PartialEq)]
+    struct User {
+        #[builder(required)]
+        name: String,
+        #[builder(optional)]
+        nickname: Option<String>,
+        #[builder(default = "18")]
+        age: u8,
+    }
+
+    let builder: UserBuilder<user_builder::name::Unset> = UserBuilder::new()
+        .with_nickname("Al".to_string())
+        .with_age(20);
+    let builder: UserBuilder<user_builder::name::Set> = builder.with_name("Alice".to_string());
+    let user = builder.with_nickname("Ally".to_string()).build();
+    assert_eq!(user.name, "Alice");
+    assert_eq!(user.nickname, Some("Ally".to_string()));
+    assert_eq!(user.age, 20);
+}
+
+#[test]
+fn named_field_states() {
+    #[derive(Builder, PartialEq)]
+    #[builder(name = "HTTPClientBuilder")]
+    struct Client {
+        #[builder(required)]
+        url: String,
+        #[builder(optional)]
+        use_auth: Option<bool>,
+        #[builder(required_if = "use_auth")]
+        api_key: Option<String>,
+    }
+
+    fn with_auth<UrlState>(
+        builder: HTTPClientBuilder<
+            UrlState,
+            http_client_builder::use_auth::Unset,
+            http_client_builder::api_key::Unset,
+        >,
+    ) -> HTTPClientBuilder<
+        UrlState,
+        http_client_builder::use_auth::Set,
+        http_client_builder::api_key::Set,
+    > {
+        builder.with_use_auth(true).with_api_key("key".to_string())
+    }
+
+    let client = with_auth(HTTPClientBuilder::new())
+        .with_url("https://example.com".to_string())
+        .build();
+    assert_eq!(client.url, "https://example.com");
+    assert_eq!(client.use_auth, Some(true));
+    assert_eq!(client.api_key, Some("key".to_string()));
+}
+
+#[test]
+fn shadowed_prelude_success() {
+    #[allow(dead_code)]
+    struct Some;
+    #[allow(dead_code)]
+    struct None;
+    #[allow(dead_code)]
+    trait Default {}
+    #[allow(dead_code)]
+    trait Into {}
+
+    #[derive(Builder, PartialEq)]
+    struct User {
+        #[builder(required)]
+        #[builder(into)]
+        name: String,
+        #[builder(optional)]
+        age: core::option::Option<u8>,
+        #[builder(default)]
+        active: bool,
+    }
+
+    let user = UserBuilder::new().with_name("Alice").with_age(20).build();
+    assert_eq!(user.name, "Alice");
+    assert_eq!(user.age, core::option::Option::Some(20));
+    assert!(!user.active);
+}
+
+mod facade {
+    pub use typesafe_builder as builder;
+}
+
+#[test]
+fn custom_crate_path_success() {
+    #[derive(Builder, PartialEq)]
+    #[builder(crate = "crate::facade::builder")]
+    struct User {
+        #[builder(required)]
+        name: String,
+        #[builder(default)]
+        age: u8,
+    }
+
+    let user = UserBuilder::new().with_name("Alice".to_string()).build();
+    assert_eq!(user.name, "Alice");
+    assert_eq!(user.age, 0);
+}
+
+#[test]
+fn enum_variant_builders_success() {
+    #[derive(Builder, PartialEq, Debug)]
+    enum Command {
+        Create {
+            #[builder(required)]
+            #[builder(into)]
+            name: String,
+            #[builder(optional)]
+            template: Option<String>,
+            #[builder(required_if = "template")]
+            template_version: Option<u32>,
+        },
+        Delete {
+            #[builder(required)]
+            id: u64,
+            #[builder(default)]
+            force: bool,
+        },
+        Quit,
+    }
+
+    let create = CommandBuilder::create().with_name("report").build();
+    assert_eq!(
+        create,
+        Command::Create {
+            name: "report".to_string(),
+            template: None,
+            template_version: None,
+        }
+    );
+
+    let create = CommandBuilder::create()
+        .with_name("report")
+        .with_template("monthly".to_string())
+        .with_template_version(2)
+        .build();
+    assert_eq!(
+        create,
+        Command::Create {
+            name: "report".to_string(),
+            template: Some("monthly".to_string()),
+            template_version: Some(2),
+        }
+    );
+
+    let delete = CommandDeleteBuilder::new().with_id(7).build();
+    assert_eq!(
+        delete,
+        Command::Delete {
+            id: 7,
+            force: false
+        }
+    );
+
+    let quit = CommandBuilder::quit().build();
+    assert_eq!(quit, Command::Quit);
+}
+
+#[test]
+fn generic_enum_variant_builders_success() {
+    #[derive(Builder, PartialEq, Debug)]
+    #[builder(name = "EventFactory")]
+    enum Event<'a, T> {
+        #[builder(name = "PayloadEventBuilder")]
+        Payload {
+            #[builder(required)]
+            source: &'a str,
+            #[builder(required)]
+            value: T,
+        },
+        Heartbeat {
+            #[builder(optional)]
+            source: Option<&'a str>,
+        },
+    }
+
+    let payload = EventFactory::payload()
+        .with_source("sensor")
+        .with_value(42)
+        .build();
+    assert_eq!(
+        payload,
+        Event::Payload {
+            source: "sensor",
+            value: 42
+        }
+    );
+
+    let heartbeat: Event<'_, i32> = PayloadEventBuilder::new()
+        .with_source("sensor")
+        .with_value(1)
+        .build();
+    assert_eq!(
+        heartbeat,
+        Event::Payload {
+            source: "sensor",
+            value: 1
+        }
+    );
+
+    let heartbeat: Event<'_, i32> = EventFactory::heartbeat().build();
+    assert_eq!(heartbeat, Event::Heartbeat { source: None });
+}
+
+#[test]
+fn tuple_struct_success() {
+    #[derive(Builder, Debug, PartialEq)]
+    struct Meters(#[builder(required)] f64);
+
+    #[derive(Builder, Debug, PartialEq)]
+    struct Point(
+        #[builder(required)] i32,
+        #[builder(default)] i32,
+        #[builder(optional)] Option<String>,
+    );
+
+    let meters = MetersBuilder::new().with_0(1.5).build();
+    assert_eq!(meters, Meters(1.5));
+
+    let point = PointBuilder::new().with_0(3).build();
+    assert_eq!(point, Point(3, 0, None));
+
+    let point = PointBuilder::new()
+        .with_2("origin".to_string())
+        .with_1(4)
+        .with_0(3)
+        .build();
+    assert_eq!(point, Point(3, 4, Some("origin".to_string())));
+}
+
+#[test]
+fn tuple_struct_named_setters_success() {
+    #[derive(Builder, Debug, PartialEq)]
+    struct Range(
+        #[builder(required)]
+        #[builder(setter(name = "start"))]
+        u32,
+        #[builder(optional)]
+        #[builder(setter(name = "end"))]
+        Option<u32>,
+        #[builder(required_if = "_1")]
+        #[builder(setter(name = "inclusive"))]
+        Option<bool>,
+    );
+
+    let range = RangeBuilder::new().start(1).build();
+    assert_eq!(range, Range(1, None, None));
+
+    let range = RangeBuilder::new().start(1).end(5).inclusive(true).build();
+    assert_eq!(range, Range(1, Some(5), Some(true)));
+}
+
+#[test]
+fn tuple_variant_builders_success() {
+    #[derive(Builder, Debug, PartialEq)]
+    enum Shape {
+        Circle(#[builder(required)] f64),
+        Rect(
+            #[builder(required)]
+            #[builder(setter(name = "width"))]
+            f64,
+            #[builder(required)]
+            #[builder(setter(name = "height"))]
+            f64,
+        ),
+    }
+
+    let circle = ShapeBuilder::circle().with_0(1.0).build();
+    assert_eq!(circle, Shape::Circle(1.0));
+
+    let rect = ShapeBuilder::rect().height(2.0).width(3.0).build();
+    assert_eq!(rect, Shape::Rect(3.0, 2.0));
+}
+
+#[test]
+fn named_field_setter_name_success() {
+    #[derive(Builder, Debug, PartialEq)]
+    struct User {
+        #[builder(optional)]
+        #[builder(setter(name = "named"))]
+        name: Option<String>,
+        #[builder(required_if = "name")]
+        age: Option<u32>,
+    }
+
+    let user = UserBuilder::new()
+        .named("Alice".to_string())
+        .with_age(30)
+        .build();
+    assert_eq!(
+        user,
+        User {
+            name: Some("Alice".to_string()),
+            age: Some(30)
+        }
+    );
+}
+
+#[test]
+fn validated_fields_try_build() {
+    fn non_empty(value: &str) -> Result<(), &'static str> {
+        if value.is_empty() {
+            Err("must not be empty")
+        } else {
+            Ok(())
+        }
+    }
+
+    fn adult(age: &u32) -> Result<(), String> {
+        if *age >= 18 {
+            Ok(())
+        } else {
+            Err(format!("{age} is under 18"))
+        }
+    }
+
+    fn valid_port(port: &u16) -> Result<(), std::num::TryFromIntError> {
+        u8::try_from(*port).map(|_| ())
+    }
+
+    #[derive(Builder, Debug, PartialEq)]
+    struct User {
+        #[builder(required)]
+        #[builder(into)]
+        #[builder(validate = "non_empty")]
+        name: String,
+        #[builder(optional)]
+        #[builder(validate = "adult")]
+        age: Option<u32>,
+        #[builder(default = "80")]
+        #[builder(validate = "valid_port")]
+        port: u16,
+        #[builder(optional)]
+        nickname: Option<String>,
+    }
+
+    let user = UserBuilder::new().with_name("Alice").try_build().unwrap();
+    assert_eq!(
+        user,
+        User {
+            name: "Alice".to_string(),
+            age: None,
+            port: 80,
+            nickname: None,
+        }
+    );
+
+    let user = UserBuilder::new()
+        .with_name("Alice")
+        .with_age(30)
+        .with_nickname("Al".to_string())
+        .try_build()
+        .unwrap();
+    assert_eq!(user.age, Some(30));
+
+    let error = UserBuilder::new().with_name("").try_build().unwrap_err();
+    assert!(matches!(error, UserBuildError::Name(_)));
+    assert_eq!(error.field(), "name");
+    assert_eq!(error.to_string(), "`name` is invalid: must not be empty");
+
+    let error = UserBuilder::new()
+        .with_name("Bob")
+        .with_age(12)
+        .try_build()
+        .unwrap_err();
+    assert_eq!(error.field(), "age");
+    assert_eq!(error.to_string(), "`age` is invalid: 12 is under 18");
+
+    let error = UserBuilder::new()
+        .with_name("Bob")
+        .with_port(u16::MAX)
+        .try_build()
+        .unwrap_err();
+    assert_eq!(error.field(), "port");
+    let source = std::error::Error::source(&error).unwrap();
+    assert!(source.is::<std::num::TryFromIntError>());
+}
+
+#[test]
+fn validated_variant_try_build() {
+    fn positive(value: &f64) -> Result<(), &'static str> {
+        if *value > 0.0 {
+            Ok(())
+        } else {
+            Err("must be positive")
+        }
+    }
+
+    #[derive(Builder, Debug, PartialEq)]
+    enum Shape {
+        Circle(
+            #[builder(required)]
+            #[builder(validate = "positive")]
+            f64,
+        ),
+        Point,
+    }
+
+    let circle = ShapeBuilder::circle().with_0(1.0).try_build().unwrap();
+    assert_eq!(circle, Shape::Circle(1.0));
+
+    let error = ShapeBuilder::circle().with_0(-1.0).try_build().unwrap_err();
+    assert!(matches!(error, ShapeCircleBuildError::Field0(_)));
+    assert_eq!(error.field(), "0");
+
+    assert_eq!(ShapeBuilder::point().build(), Shape::Point);
+}
+
+#[test]
+fn struct_validator_success() {
+    #[derive(Debug, PartialEq)]
+    enum PoolError {
+        EmptyRange { min: u32, max: u32 },
+    }
+
+    #[derive(Builder, Debug, PartialEq)]
+    #[builder(validate_with = "Self::check", error = "PoolError")]
+    struct Pool {
+        #[builder(default = "1")]
+        min_connections: u32,
+        #[builder(required)]
+        max_connections: u32,
+    }
+
+    impl Pool {
+        fn check(&self) -> Result<(), PoolError> {
+            if self.min_connections <= self.max_connections {
+                Ok(())
+            } else {
+                Err(PoolError::EmptyRange {
+                    min: self.min_connections,
+                    max: self.max_connections,
+                })
+            }
+        }
+    }
+
+    let pool = PoolBuilder::new().with_max_connections(10).build();
+    assert_eq!(
+        pool,
+        Ok(Pool {
+            min_connections: 1,
+            max_connections: 10
+        })
+    );
+
+    let pool = PoolBuilder::new()
+        .with_min_connections(5)
+        .with_max_connections(2)
+        .build();
+    assert_eq!(pool, Err(PoolError::EmptyRange { min: 5, max: 2 }));
+
+    #[derive(Builder, Debug, PartialEq)]
+    #[builder(validate_with = "Self::check", error = "&'static str")]
+    struct Bounded<T>
+    where
+        T: PartialOrd,
+    {
+        #[builder(required)]
+        value: T,
+        #[builder(optional)]
+        max: Option<T>,
+    }
+
+    impl<T: PartialOrd> Bounded<T> {
+        fn check(&self) -> Result<(), &'static str> {
+            match &self.max {
+                Some(max) if self.value > *max => Err("value exceeds max"),
+                _ => Ok(()),
+            }
+        }
+    }
+
+    let bounded = BoundedBuilder::new().with_value(3).with_max(5).build();
+    assert_eq!(
+        bounded,
+        Ok(Bounded {
+            value: 3,
+            max: Some(5)
+        })
+    );
+
+    let bounded = BoundedBuilder::new().with_value(7).with_max(5).build();
+    assert_eq!(bounded, Err("value exceeds max"));
+}
+
+#[test]
+fn struct_validator_with_field_validators_success() {
+    #[derive(Debug)]
+    enum RangeError {
+        Field(RangeBuildError),
+        Reversed,
+    }
+
+    impl From<RangeBuildError> for RangeError {
+        fn from(error: RangeBuildError) -> Self {
+            RangeError::Field(error)
+        }
+    }
+
+    fn check_range(range: &Range) -> Result<(), RangeError> {
+        if range.start <= range.end {
+            Ok(())
+        } else {
+            Err(RangeError::Reversed)
+        }
+    }
+
+    fn finite(value: &f64) -> Result<(), &'static str> {
+        if value.is_finite() {
+            Ok(())
+        } else {
+            Err("must be finite")
+        }
+    }
+
+    #[derive(Builder, Debug, PartialEq)]
+    #[builder(validate_with = "check_range", error = "RangeError")]
+    struct Range {
+        #[builder(required)]
+        #[builder(validate = "finite")]
+        start: f64,
+        #[builder(required)]
+        end: f64,
+    }
+
+    let range = RangeBuilder::new().with_start(1.0).with_end(2.0).build();
+    assert_eq!(
+        range.unwrap(),
+        Range {
+            start: 1.0,
+            end: 2.0
+        }
+    );
+
+    let error = RangeBuilder::new()
+        .with_start(2.0)
+        .with_end(1.0)
+        .build()
+        .unwrap_err();
+    assert!(matches!(error, RangeError::Reversed));
+
+    let error = RangeBuilder::new()
+        .with_start(f64::NAN)
+        .with_end(1.0)
+        .build()
+        .unwrap_err();
+    match error {
+        RangeError::Field(error) => assert_eq!(error.field(), "start"),
+        RangeError::Reversed => panic!("field validators run first"),
+    }
+}
+
+#[test]
+fn conflicting_fields_success() {
+    #[derive(Builder, Debug, PartialEq)]
+    struct Credentials {
+        #[builder(optional)]
+        #[builder(conflicts_with = "oauth_token")]
+        api_key: Option<String>,
+        #[builder(optional)]
+        oauth_token: Option<String>,
+        #[builder(optional)]
+        #[builder(conflicts_with = "api_key || oauth_token")]
+        anonymous: Option<bool>,
+        #[builder(default)]
+        #[builder(conflicts_with = "!api_key")]
+        key_rotation: bool,
+    }
+
+    let credentials = CredentialsBuilder::new().build();
+    assert_eq!(
+        credentials,
+        Credentials {
+            api_key: None,
+            oauth_token: None,
+            anonymous: None,
+            key_rotation: false,
+        }
+    );
+
+    let credentials = CredentialsBuilder::new()
+        .with_api_key("key".to_string())
+        .with_key_rotation(true)
+        .build();
+    assert_eq!(credentials.api_key, Some("key".to_string()));
+    assert!(credentials.key_rotation);
+
+    let credentials = CredentialsBuilder::new()
+        .with_oauth_token("token".to_string())
+        .build();
+    assert_eq!(credentials.oauth_token, Some("token".to_string()));
+
+    let credentials = CredentialsBuilder::new().with_anonymous(true).build();
+    assert_eq!(credentials.anonymous, Some(true));
+}
+
+#[test]
+fn forbidden_if_success() {
+    #[derive(Builder, Debug, PartialEq)]
+    struct Login {
+        #[builder(required)]
+        user: String,
+        #[builder(optional)]
+        sso_provider: Option<String>,
+        #[builder(forbidden_if = "sso_provider")]
+        password: Option<String>,
+        #[builder(forbidden_if = "!sso_provider")]
+        #[builder(into)]
+        sso_scope: Option<String>,
+    }
+
+    let login = LoginBuilder::new()
+        .with_user("alice".to_string())
+        .with_password("secret".to_string())
+        .build();
+    assert_eq!(
+        login,
+        Login {
+            user: "alice".to_string(),
+            sso_provider: None,
+            password: Some("secret".to_string()),
+            sso_scope: None,
+        }
+    );
+
+    let login = LoginBuilder::new()
+        .with_user("bob".to_string())
+        .with_sso_provider("github".to_string())
+        .with_sso_scope("read")
+        .build();
+    assert_eq!(login.password, None);
+    assert_eq!(login.sso_scope, Some("read".to_string()));
+
+    let login = LoginBuilder::new().with_user("carol".to_string()).build();
+    assert_eq!(login.password, None);
+}
+
+#[test]
+fn field_groups_success() {
+    #[derive(Builder, Debug, PartialEq)]
+    #[builder(group(name = "source", exactly_one = [file, url, inline_data]))]
+    #[builder(group(name = "contact", at_least_one = [email, phone]))]
+    #[builder(group(name = "compression", at_most_one = [gzip, zstd]))]
+    struct Upload {
+        #[builder(optional)]
+        file: Option<String>,
+        #[builder(optional)]
+        url: Option<String>,
+        #[builder(optional)]
+        inline_data: Option<Vec<u8>>,
+        #[builder(optional)]
+        email: Option<String>,
+        #[builder(optional)]
+        phone: Option<String>,
+        #[builder(default)]
+        gzip: bool,
+        #[builder(default)]
+        zstd: bool,
+    }
+
+    let upload = UploadBuilder::new()
+        .with_url("https://example.com".to_string())
+        .with_email("a@example.com".to_string())
+        .build();
+    assert_eq!(
+        upload,
+        Upload {
+            file: None,
+            url: Some("https://example.com".to_string()),
+            inline_data: None,
+            email: Some("a@example.com".to_string()),
+            phone: None,
+            gzip: false,
+            zstd: false,
+        }
+    );
+
+    let upload = UploadBuilder::new()
+        .with_inline_data(vec![1, 2, 3])
+        .with_email("a@example.com".to_string())
+        .with_phone("555".to_string())
+        .with_zstd(true)
+        .build();
+    assert_eq!(upload.inline_data, Some(vec![1, 2, 3]));
+    assert!(upload.zstd);
+}
+
+#[test]
+fn variant_field_groups_success() {
+    #[derive(Builder, Debug, PartialEq)]
+    enum Target {
+        #[builder(group(name = "address", exactly_one = [_0, _1]))]
+        Remote(
+            #[builder(optional)] Option<String>,
+            #[builder(optional)] Option<std::net::Ipv4Addr>,
+        ),
+        Local,
+    }
+
+    let target = TargetBuilder::remote()
+        .with_1(std::net::Ipv4Addr::LOCALHOST)
+        .build();
+    assert_eq!(
+        target,
+        Target::Remote(None, Some(std::net::Ipv4Addr::LOCALHOST))
+    );
+    assert_eq!(TargetBuilder::local().build(), Target::Local);
+}
+
+#[test]
+fn wide_field_group_success() {
+    #[derive(Builder, Debug, PartialEq)]
+    #[builder(group(name = "choice", exactly_one = [a, b, c, d, e, f, g, h, i, j, k, l, m, n, o, p, q, r, s, t, u, v, w, x]))]
+    struct Wide {
+        #[builder(optional)]
+        a: Option<u8>,
+        #[builder(optional)]
+        b: Option<u8>,
+        #[builder(optional)]
+        c: Option<u8>,
+        #[builder(optional)]
+        d: Option<u8>,
+        #[builder(optional)]
+        e: Option<u8>,
+        #[builder(optional)]
+        f: Option<u8>,
+        #[builder(optional)]
+        g: Option<u8>,
+        #[builder(optional)]
+        h: Option<u8>,
+        #[builder(optional)]
+        i: Option<u8>,
+        #[builder(optional)]
+        j: Option<u8>,
+        #[builder(optional)]
+        k: Option<u8>,
+        #[builder(optional)]
+        l: Option<u8>,
+        #[builder(optional)]
+        m: Option<u8>,
+        #[builder(optional)]
+        n: Option<u8>,
+        #[builder(optional)]
+        o: Option<u8>,
+        #[builder(optional)]
+        p: Option<u8>,
+        #[builder(optional)]
+        q: Option<u8>,
+        #[builder(optional)]
+        r: Option<u8>,
+        #[builder(optional)]
+        s: Option<u8>,
+        #[builder(optional)]
+        t: Option<u8>,
+        #[builder(optional)]
+        u: Option<u8>,
+        #[builder(optional)]
+        v: Option<u8>,
+        #[builder(optional)]
+        w: Option<u8>,
+        #[builder(optional)]
+        x: Option<u8>,
+        #[builder(
+            required_if = "count(a, b, c, d, e, f, g, h, i, j, k, l, m, n, o, p, q, r, s, t, u, v, w, x) == 1"
+        )]
+        chosen: Option<()>,
+    }
+
+    let wide = WideBuilder::new().with_x(24).with_chosen(()).build();
+    assert_eq!(wide.x, Some(24));
+    assert_eq!(wide.a, None);
+    assert_eq!(wide.chosen, Some(()));
+}
+
+#[test]
+fn wide_condition_success() {
+    #[derive(Builder, Debug, PartialEq)]
+    struct Wide {
+        #[builder(optional)]
+        a: Option<u8>,
+        #[builder(optional)]
+        b: Option<u8>,
+        #[builder(optional)]
+        c: Option<u8>,
+        #[builder(optional)]
+        d: Option<u8>,
+        #[builder(optional)]
+        e: Option<u8>,
+        #[builder(optional)]
+        f: Option<u8>,
+        #[builder(optional)]
+        g: Option<u8>,
+        #[builder(optional)]
+        h: Option<u8>,
+        #[builder(optional)]
+        i: Option<u8>,
+        #[builder(optional)]
+        j: Option<u8>,
+        #[builder(optional)]
+        k: Option<u8>,
+        #[builder(optional)]
+        l: Option<u8>,
+        #[builder(optional)]
+        m: Option<u8>,
+        #[builder(optional)]
+        n: Option<u8>,
+        #[builder(optional)]
+        o: Option<u8>,
+        #[builder(optional)]
+        p: Option<u8>,
+        #[builder(optional)]
+        q: Option<u8>,
+        #[builder(optional)]
+        r: Option<u8>,
+        #[builder(optional)]
+        s: Option<u8>,
+        #[builder(optional)]
+        t: Option<u8>,
+        #[builder(optional)]
+        u: Option<u8>,
+        #[builder(optional)]
+        v: Option<u8>,
+        #[builder(optional)]
+        w: Option<u8>,
+        #[builder(optional)]
+        x: Option<u8>,
+        #[builder(
+            required_if = "count(a, b, c, d, e, f, g, h, i, j, k, l, m, n, o, p, q, r, s, t, u, v, w, x) == 1"
+        )]
+        chosen: Option<()>,
+    }
+
+    let wide = WideBuilder::new().with_x(24).with_chosen(()).build();
+    assert_eq!(wide.x, Some(24));
+    assert_eq!(wide.a, None);
+    assert_eq!(wide.chosen, Some(()));
+}
+
+#[test]
+fn extended_condition_operators_success() {
+    #[derive(Builder, Debug, PartialEq)]
+    struct Deploy {
+        #[builder(optional)]
+        image: Option<String>,
+        #[builder(optional)]
+        dockerfile: Option<String>,
+        #[builder(optional)]
+        registry: Option<String>,
+        #[builder(required_if = "image ^ dockerfile")]
+        tag: Option<String>,
+        #[builder(required_if = "registry -> !image")]
+        context: Option<String>,
+        #[builder(required_if = "at_least(2, image, dockerfile, registry)")]
+        reviewer: Option<String>,
+        #[builder(optional_if = "count(image, dockerfile, registry) == 0")]
+        name: Option<String>,
+    }
+
+    // Nothing is set: `registry -> !image` holds vacuously and `count(..) == 0`.
+    let deploy = DeployBuilder::new().with_context(".".to_string()).build();
+    assert_eq!(deploy.context, Some(".".to_string()));
+    assert_eq!(deploy.name, None);
+
+    // Exactly one of `image` and `dockerfile` requires `tag`, and any of the
+    // three requires `name`.
+    let deploy = DeployBuilder::new()
+        .with_image("nginx".to_string())
+        .with_tag("latest".to_string())
+        .with_context(".".to_string())
+        .with_name("web".to_string())
+        .build();
+    assert_eq!(deploy.tag, Some("latest".to_string()));
+
+    // `registry` with `image` violates the premise of `registry -> !image`,
+    // so `context` becomes optional, but two sources require a `reviewer`.
+    let deploy = DeployBuilder::new()
+        .with_image("nginx".to_string())
+        .with_registry("ghcr.io".to_string())
+        .with_tag("latest".to_string())
+        .with_reviewer("alice".to_string())
+        .with_name("web".to_string())
+        .build();
+    assert_eq!(deploy.context, None);
+    assert_eq!(deploy.reviewer, Some("alice".to_string()));
+
+    // Both `image` and `dockerfile` make `tag` optional again.
+    let deploy = DeployBuilder::new()
+        .with_image("nginx".to_string())
+        .with_dockerfile("Dockerfile".to_string())
+        .with_context(".".to_string())
+        .with_reviewer("alice".to_string())
+        .with_name("web".to_string())
+        .build();
+    assert_eq!(deploy.tag, None);
+}
+
+#[test]
+fn count_comparisons_success() {
+    #[derive(Builder, Debug, PartialEq)]
+    struct Flags {
+        #[builder(default)]
+        a: bool,
+        #[builder(default)]
+        b: bool,
+        #[builder(default)]
+        c: bool,
+        #[builder(optional)]
+        #[builder(conflicts_with = "count(a, b, c) > 1")]
+        single: Option<()>,
+        #[builder(required_if = "count(a, b, c) != 1 && count(a, b, c) >= 2")]
+        many: Option<()>,
+        #[builder(required_if = "at_least(2, a -> c, c)")]
+        both: Option<()>,
+    }
+
+    let flags = FlagsBuilder::new().with_a(true).with_single(()).build();
+    assert_eq!(flags.single, Some(()));
+    let flags = FlagsBuilder::new()
+        .with_a(true)
+        .with_c(true)
+        .with_many(())
+        .with_both(())
+        .build();
+    assert_eq!(flags.many, Some(()));
+    assert_eq!(flags.both, Some(()));
+}
+
+#[test]
+fn named_conditions_success() {
+    #[derive(Builder, Debug, PartialEq)]
+    #[builder(condition(secure = "use_auth || use_https"))]
+    #[builder(condition(needs_token = "secure && !api_key"))]
+    struct ApiClient {
+        #[builder(default)]
+        use_auth: bool,
+        #[builder(default)]
+        use_https: bool,
+        #[builder(optional)]
+        api_key: Option<String>,
+        #[builder(required_if = "needs_token")]
+        token: Option<String>,
+        #[builder(optional_if = "!secure")]
+        certificate: Option<String>,
+    }
+
+    let client = ApiClientBuilder::new().build();
+    assert_eq!(client.token, None);
+
+    let client = ApiClientBuilder::new()
+        .with_use_https(true)
+        .with_token("token".to_string())
+        .with_certificate("cert".to_string())
+        .build();
+    assert_eq!(client.token, Some("token".to_string()));
+
+    let client = ApiClientBuilder::new()
+        .with_use_auth(true)
+        .with_api_key("key".to_string())
+        .with_certificate("cert".to_string())
+        .build();
+    assert_eq!(client.token, None);
+}
+
+#[test]
+fn variant_named_conditions_success() {
+    #[derive(Builder, Debug, PartialEq)]
+    enum Connection {
+        #[builder(condition(authenticated = "user || token"))]
+        Remote {
+            #[builder(optional)]
+            user: Option<String>,
+            #[builder(optional)]
+            token: Option<String>,
+            #[builder(required_if = "authenticated")]
+            realm: Option<String>,
+        },
+    }
+
+    let connection = ConnectionBuilder::remote()
+        .with_token("token".to_string())
+        .with_realm("main".to_string())
+        .build();
+    assert_eq!(
+        connection,
+        Connection::Remote {
+            user: None,
+            token: Some("token".to_string()),
+            realm: Some("main".to_string()),
+        }
+    );
+}
+
+#[test]
+fn flag_fields_success() {
+    #[derive(Builder, Debug, PartialEq)]
+    struct ApiClient {
+        #[builder(flag)]
+        use_auth: bool,
+        #[builder(flag)]
+        verbose: Option<bool>,
+        #[builder(required_if = "use_auth")]
+        secret: Option<String>,
+        #[builder(optional)]
+        #[builder(conflicts_with = "verbose")]
+        log_file: Option<String>,
+    }
+
+    // Disabling a flag keeps its condition false, unlike setting a value.
+    let client = ApiClientBuilder::new().disable_use_auth().build();
+    assert_eq!(
+        client,
+        ApiClient {
+            use_auth: false,
+            verbose: None,
+            secret: None,
+            log_file: None,
+        }
+    );
+
+    let client = ApiClientBuilder::new()
+        .enable_use_auth()
+        .with_secret("secret".to_string())
+        .disable_verbose()
+        .with_log_file("out.log".to_string())
+        .build();
+    assert!(client.use_auth);
+    assert_eq!(client.verbose, Some(false));
+
+    // The last call wins.
+    let client = ApiClientBuilder::new()
+        .enable_use_auth()
+        .disable_use_auth()
+        .enable_verbose()
+        .build();
+    assert!(!client.use_auth);
+    assert_eq!(client.verbose, Some(true));
+}
+
+#[test]
+fn flag_setter_name_success() {
+    #[derive(Builder, Debug, PartialEq)]
+    struct Request(
+        #[builder(flag)]
+        #[builder(setter(name = "retries"))]
+        bool,
+        #[builder(required_if = "_0")] Option<u8>,
+    );
+
+    let request = RequestBuilder::new().enable_retries().with_1(3).build();
+    assert_eq!(request, Request(true, Some(3)));
+    assert_eq!(
+        RequestBuilder::new().disable_retries().build(),
+        Request(false, None)
+    );
+}
+
+#[test]
+fn selector_fields_success() {
+    #[derive(Debug, PartialEq)]
+    enum Transport {
+        Tcp,
+        Unix,
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum Compression {
+        Gzip,
+        Zstd,
+    }
+
+    #[derive(Builder, Debug, PartialEq)]
+    struct Server {
+        #[builder(selector(Tcp, Unix))]
+        transport: Transport,
+        #[builder(required_if = "transport == Tcp")]
+        #[builder(conflicts_with = "transport != Tcp")]
+        port: Option<u16>,
+        #[builder(required_if = "transport == Unix")]
+        #[builder(conflicts_with = "transport == Tcp")]
+        socket_path: Option<String>,
+        #[builder(selector(Gzip, Zstd))]
+        compression: Option<Compression>,
+        #[builder(optional_if = "compression != Zstd")]
+        level: Option<u8>,
+    }
+
+    let server = ServerBuilder::new()
+        .with_transport_tcp()
+        .with_port(8080)
+        .build();
+    assert_eq!(
+        server,
+        Server {
+            transport: Transport::Tcp,
+            port: Some(8080),
+            socket_path: None,
+            compression: None,
+            level: None,
+        }
+    );
+
+    let server = ServerBuilder::new()
+        .with_transport_unix()
+        .with_socket_path("/tmp/server.sock".to_string())
+        .with_compression_zstd()
+        .with_level(3)
+        .build();
+    assert_eq!(server.transport, Transport::Unix);
+    assert_eq!(server.compression, Some(Compression::Zstd));
+    assert_eq!(server.level, Some(3));
+
+    // The last chosen variant wins.
+    let server = ServerBuilder::new()
+        .with_transport_unix()
+        .with_transport_tcp()
+        .with_port(22)
+        .with_compression_gzip()
+        .build();
+    assert_eq!(server.transport, Transport::Tcp);
+    assert_eq!(server.compression, Some(Compression::Gzip));
+}
+
+#[test]
+fn conditional_defaults_success() {
+    #[derive(Builder, Debug, PartialEq)]
+    struct Listener {
+        #[builder(optional)]
+        tls: Option<()>,
+        #[builder(required_if = "tls")]
+        #[builder(default = "String::from(\"none\")")]
+        cert_path: String,
+        #[builder(optional_if = "!tls")]
+        #[builder(default = "80")]
+        port: u16,
+        #[builder(default_if = "!tls")]
+        ciphers: Vec<String>,
+        #[builder(default_if = "!tls", default = "Some(3)")]
+        retries: Option<u8>,
+    }
+
+    // Without `tls` nothing is required and every default is used.
+    let listener = ListenerBuilder::new().build();
+    assert_eq!(
+        listener,
+        Listener {
+            tls: None,
+            cert_path: "none".to_string(),
+            port: 80,
+            ciphers: Vec::new(),
+            retries: Some(3),
+        }
+    );
+
+    // Values set while not required replace the defaults.
+    let listener = ListenerBuilder::new()
+        .with_port(8080)
+        .with_retries(None)
+        .build();
+    assert_eq!(listener.port, 8080);
+    assert_eq!(listener.retries, None);
+
+    let listener = ListenerBuilder::new()
+        .with_tls(())
+        .with_cert_path("cert.pem".to_string())
+        .with_port(443)
+        .with_ciphers(vec!["TLS_AES_128_GCM_SHA256".to_string()])
+        .with_retries(Some(5))
+        .build();
+    assert_eq!(listener.cert_path, "cert.pem");
+    assert_eq!(listener.port, 443);
+    assert_eq!(listener.ciphers.len(), 1);
+    assert_eq!(listener.retries, Some(5));
+}
+
+#[test]
+fn computed_defaults_success() {
+    #[derive(Builder, Debug, PartialEq)]
+    struct Account {
+        // Defaults may refer to fields declared after them.
+        #[builder(default = "format!(\"{} <{}>\", display_name, email)")]
+        label: String,
+        #[builder(default = "name.clone()")]
+        display_name: String,
+        #[builder(required)]
+        name: String,
+        #[builder(required)]
+        email: String,
+        #[builder(flag)]
+        tls: bool,
+        #[builder(default_if = "!tls", default = "if *tls { 443 } else { 80 }")]
+        port: u16,
+    }
+
+    let account = AccountBuilder::new()
+        .with_name("alice".to_string())
+        .with_email("alice@example.com".to_string())
+        .disable_tls()
+        .build();
+    assert_eq!(
+        account,
+        Account {
+            label: "alice <alice@example.com>".to_string(),
+            display_name: "alice".to_string(),
+            name: "alice".to_string(),
+            email: "alice@example.com".to_string(),
+            tls: false,
+            port: 80,
+        }
+    );
+
+    // Set values are what the defaults see.
+    let account = AccountBuilder::new()
+        .with_name("alice".to_string())
+        .with_display_name("Alice".to_string())
+        .with_email("alice@example.com".to_string())
+        .enable_tls()
+        .with_port(8443)
+        .build();
+    assert_eq!(account.label, "Alice <alice@example.com>");
+    assert_eq!(account.port, 8443);
+}
+
+#[test]
+fn computed_default_references_success() {
+    struct Limits {
+        label: u16,
+    }
+
+    #[derive(Builder)]
+    struct Job {
+        // Format strings capture fields by name.
+        #[builder(default = "format!(\"{name}:{port}/{retries}\")")]
+        label: String,
+        #[builder(required)]
+        name: String,
+        // Neither struct literal keys nor closure parameters refer to fields.
+        #[builder(default = "Limits { label: 8080 }.label")]
+        port: u16,
+        #[builder(default = "[1u8, 2].iter().map(|label| label * 2).sum()")]
+        retries: u8,
+    }
+
+    let job = JobBuilder::new().with_name("sync".to_string()).build();
+    assert_eq!(job.name, "sync");
+    assert_eq!(job.label, "sync:8080/6");
+    assert_eq!(job.port, 8080);
+    assert_eq!(job.retries, 6);
+}
+
+#[test]
+fn computed_defaults_with_validators_success() {
+    fn positive(value: &u32) -> Result<(), String> {
+        if *value > 0 {
+            Ok(())
+        } else {
+            Err("must be positive".to_string())
+        }
+    }
+
+    #[derive(Builder, Debug, PartialEq)]
+    struct Pool {
+        #[builder(required)]
+        #[builder(validate = "positive")]
+        min: u32,
+        #[builder(default = "*min * 2")]
+        #[builder(validate = "positive")]
+        max: u32,
+    }
+
+    let pool = PoolBuilder::new().with_min(4).try_build().unwrap();
+    assert_eq!(pool, Pool { min: 4, max: 8 });
+    let error = PoolBuilder::new()
+        .with_min(4)
+        .with_max(0)
+        .try_build()
+        .unwrap_err();
+    assert!(matches!(error, PoolBuildError::Max(_)));
+    assert_eq!(error.to_string(), "`max` is invalid: must be positive");
+}
+
+#[test]
+fn lazy_defaults_evaluated_once_when_unset() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static EVALUATIONS: AtomicUsize = AtomicUsize::new(0);
+
+    fn next_id() -> u64 {
+        EVALUATIONS.fetch_add(1, Ordering::SeqCst) as u64 + 100
+    }
+
+    #[derive(Builder, Debug, PartialEq)]
+    struct Session {
+        #[builder(default = "next_id()")]
+        id: u64,
+        #[builder(optional)]
+        user: Option<String>,
+        #[builder(optional_if = "!user", default = "next_id()")]
+        token: u64,
+    }
+
+    // Creating and filling the builder evaluates nothing.
+    let builder = SessionBuilder::new().with_id(1);
+    assert_eq!(EVALUATIONS.load(Ordering::SeqCst), 0);
+
+    // Overridden defaults are never evaluated.
+    let session = builder.with_token(2).build();
+    assert_eq!(session.id, 1);
+    assert_eq!(session.token, 2);
+    assert_eq!(EVALUATIONS.load(Ordering::SeqCst), 0);
+
+    // Each default of an unset field is evaluated exactly once.
+    let session = SessionBuilder::new().build();
+    assert_eq!(EVALUATIONS.load(Ordering::SeqCst), 2);
+    assert_eq!(session.id, 100);
+    assert_eq!(session.token, 101);
+
+    let session = SessionBuilder::new().with_token(7).build();
+    assert_eq!(EVALUATIONS.load(Ordering::SeqCst), 3);
+    assert_eq!(session.id, 102);
+    assert_eq!(session.token, 7);
+}
+
+#[test]
+fn default_from_struct_success() {
+    #[derive(Builder, Debug, PartialEq)]
+    #[builder(default_from_struct)]
+    struct Config {
+        #[builder(required)]
+        name: String,
+        port: u16,
+        hosts: Vec<String>,
+        #[builder(default = "3")]
+        retries: u8,
+    }
+
+    impl Default for Config {
+        fn default() -> Self {
+            Config {
+                name: "default".to_string(),
+                port: 8080,
+                hosts: vec!["localhost".to_string()],
+                retries: 0,
+            }
+        }
+    }
+
+    // Unannotated fields come from `Config::default()`, annotated ones keep
+    // their own defaults.
+    let config = ConfigBuilder::new().with_name("app".to_string()).build();
+    assert_eq!(
+        config,
+        Config {
+            name: "app".to_string(),
+            port: 8080,
+            hosts: vec!["localhost".to_string()],
+            retries: 3,
+        }
+    );
+
+    let config = ConfigBuilder::new()
+        .with_name("app".to_string())
+        .with_port(443)
+        .with_hosts(Vec::new())
+        .build();
+    assert_eq!(config.port, 443);
+    assert!(config.hosts.is_empty());
+}
+
+#[test]
+fn default_from_function_called_once_success() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+    #[derive(Builder, Debug, PartialEq)]
+    #[builder(default_from = "Self::base")]
+    struct Limits<T>
+    where
+        T: Default,
+    {
+        #[builder(required)]
+        label: T,
+        soft: u32,
+        hard: u32,
+        #[builder(default = "*soft * 2")]
+        burst: u32,
+    }
+
+    impl<T: Default> Limits<T> {
+        fn base() -> Self {
+            CALLS.fetch_add(1, Ordering::SeqCst);
+            Limits {
+                label: T::default(),
+                soft: 10,
+                hard: 20,
+                burst: 0,
+            }
+        }
+    }
+
+    // Nothing is taken from `base` once every field it provides is set.
+    let limits = LimitsBuilder::new()
+        .with_label("api".to_string())
+        .with_soft(1)
+        .with_hard(2)
+        .build();
+    assert_eq!(CALLS.load(Ordering::SeqCst), 0);
+    assert_eq!(limits.burst, 2);
+
+    // Otherwise it is called once for all of them.
+    let limits = LimitsBuilder::new().with_label("api".to_string()).build();
+    assert_eq!(CALLS.load(Ordering::SeqCst), 1);
+    assert_eq!(
+        limits,
+        Limits {
+            label: "api".to_string(),
+            soft: 10,
+            hard: 20,
+            burst: 20,
+        }
+    );
+}
+
+#[test]
+fn default_from_tuple_struct_success() {
+    #[derive(Builder, Debug, Default, PartialEq)]
+    #[builder(default_from_struct)]
+    struct Pair(#[builder(required)] u8, u8, String);
+
+    assert_eq!(
+        PairBuilder::new().with_0(1).build(),
+        Pair(1, 0, String::new())
+    );
+    assert_eq!(
+        PairBuilder::new().with_0(1).with_2("b".to_string()).build(),
+        Pair(1, 0, "b".to_string())
+    );
+}
+
+#[test]
+fn default_requirement_success() {
+    #[derive(Builder, Debug, PartialEq)]
+    #[builder(fields(default_requirement = "required"))]
+    struct Endpoint {
+        host: String,
+        port: u16,
+        // `Option` fields are required too, unless annotated.
+        path: Option<String>,
+        #[builder(optional)]
+        query: Option<String>,
+    }
+
+    let endpoint = EndpointBuilder::new()
+        .with_host("localhost".to_string())
+        .with_port(80)
+        .with_path(Some("/".to_string()))
+        .build();
+    assert_eq!(endpoint.path, Some("/".to_string()));
+    assert_eq!(endpoint.query, None);
+
+    #[derive(Builder, Debug, PartialEq)]
+    #[builder(fields(default_requirement = "optional"))]
+    struct Filters {
+        name: Option<String>,
+        #[builder(required)]
+        limit: usize,
+        #[builder(default = "10")]
+        offset: usize,
+    }
+
+    assert_eq!(
+        FiltersBuilder::new().with_limit(5).build(),
+        Filters {
+            name: None,
+            limit: 5,
+            offset: 10,
+        }
+    );
+}
+
+#[test]
+fn inferred_requirement_success() {
+    #[derive(Builder, Debug, PartialEq)]
+    #[builder(fields(default_requirement = "infer"))]
+    struct User {
+        name: String,
+        email: Option<String>,
+        #[builder(required_if = "email")]
+        verified: Option<bool>,
+        #[builder(default)]
+        roles: Vec<String>,
+    }
+
+    let user = UserBuilder::new().with_name("Alice".to_string()).build();
+    assert_eq!(
+        user,
+        User {
+            name: "Alice".to_string(),
+            email: None,
+            verified: None,
+            roles: Vec::new(),
+        }
+    );
+
+    let user = UserBuilder::new()
+        .with_name("Bob".to_string())
+        .with_email("bob@example.com".to_string())
+        .with_verified(true)
+        .build();
+    assert_eq!(user.verified, Some(true));
+
+    // Variants inherit the requirement declared on the enum, or override it.
+    #[derive(Builder, Debug, PartialEq)]
+    #[builder(fields(default_requirement = "infer"))]
+    enum Shape {
+        Circle {
+            radius: f64,
+            label: Option<String>,
+        },
+        #[builder(fields(default_requirement = "optional"))]
+        Square {
+            side: Option<f64>,
+        },
+    }
+
+    assert_eq!(
+        ShapeCircleBuilder::new().with_radius(1.0).build(),
+        Shape::Circle {
+            radius: 1.0,
+            label: None,
+        }
+    );
+    assert_eq!(
+        ShapeSquareBuilder::new().build(),
+        Shape::Square { side: None }
+    );
+}
+
+#[test]
+fn function_builder_success() {
+    #[builder]
+    fn connect(
+        #[builder(required, into)] host: String,
+        #[builder(default = "5432")] port: u16,
+        #[builder(optional)] user: Option<String>,
+        #[builder(required_if = "user")] password: Option<String>,
+        #[builder(flag)] tls: bool,
+    ) -> String {
+        let credentials = match (user, password) {
+            (Some(user), Some(password)) => format!("{user}:{password}@"),
+            _ => String::new(),
+        };
+        let scheme = if tls { "postgres+tls" } else { "postgres" };
+        format!("{scheme}://{credentials}{host}:{port}")
+    }
+
+    assert_eq!(
+        connect().with_host("localhost").call(),
+        "postgres://localhost:5432"
+    );
+    assert_eq!(
+        connect()
+            .with_port(6543)
+            .with_user("admin".to_string())
+            .enable_tls()
+            .with_password("secret".to_string())
+            .with_host("db")
+            .call(),
+        "postgres+tls://admin:secret@db:6543"
+    );
+}
+
+#[test]
+fn function_builder_unannotated_arguments_success() {
+    // Unannotated arguments are required, or optional if of type `Option<T>`.
+    #[builder]
+    fn connect(
+        host: String,
+        #[builder(default = "5432")] port: u16,
+        user: Option<String>,
+    ) -> String {
+        match user {
+            Some(user) => format!("{user}@{host}:{port}"),
+            None => format!("{host}:{port}"),
+        }
+    }
+
+    assert_eq!(
+        connect().with_host("localhost".to_string()).call(),
+        "localhost:5432"
+    );
+    assert_eq!(
+        connect()
+            .with_host("db".to_string())
+            .with_port(6543)
+            .with_user("admin".to_string())
+            .call(),
+        "admin@db:6543"
+    );
+}
+
+#[test]
+fn generic_function_builder_success() {
+    #[builder(name = "TotalBuilder")]
+    fn total<T: Copy + std::iter::Sum<T>>(
+        #[builder(required)] items: &[T],
+        #[builder(optional)] skip: Option<usize>,
+    ) -> T {
+        items.iter().skip(skip.unwrap_or(0)).copied().sum()
+    }
+
+    let builder: TotalBuilder<'_, u32, _> = total();
+    assert_eq!(builder.with_items(&[1, 2, 3]).call(), 6);
+    assert_eq!(total().with_items(&[1.5, 2.5]).with_skip(1).call(), 2.5);
+
+    // Type parameters appearing only in the return type are given to `call`.
+    #[builder]
+    fn empty<T: Default>() -> T {
+        T::default()
+    }
+
+    let value: Vec<u8> = empty().call();
+    assert!(value.is_empty());
+}
+
+#[test]
+fn function_builder_elided_lifetimes_success() {
+    // Elided lifetimes of the arguments are named by the builder, and the
+    // return type borrows from the only one.
+    #[builder]
+    fn trimmed(#[builder(required)] text: &str, #[builder(default = "' '")] end: char) -> &str {
+        text.trim_end_matches(end)
+    }
+
+    let text = String::from("report...");
+    assert_eq!(trimmed().with_text(&text).with_end('.').call(), "report");
+    assert_eq!(trimmed().with_text("done  ").call(), "done");
+
+    #[builder]
+    fn greet(#[builder(required)] names: &[&str]) -> String {
+        format!("Hello, {}", names.join(" and "))
+    }
+
+    assert_eq!(
+        greet().with_names(&["Alice", "Bob"]).call(),
+        "Hello, Alice and Bob"
+    );
+}
+
+#[test]
+#[allow(deprecated)]
+fn function_builder_forwards_attributes() {
+    // The builder is configured out together with the function.
+    #[cfg(any())]
+    #[builder]
+    fn unavailable(#[builder(required)] value: MissingType) {}
+
+    #[deprecated(note = "use `dial` instead")]
+    #[must_use]
+    #[builder]
+    fn connect(#[builder(required)] host: &str) -> String {
+        format!("tcp://{host}")
+    }
+
+    assert_eq!(connect().with_host("db").call(), "tcp://db");
+}
+
+#[test]
+fn function_builder_keeps_the_original_function() {
+    // The original function is still called by name from its own body.
+    #[builder]
+    fn factorial(#[builder(required)] mut n: u64) -> u64 {
+        if n <= 1 {
+            return 1;
+        }
+        n -= 1;
+        (n + 1) * factorial(n)
+    }
+
+    assert_eq!(factorial().with_n(5).call(), 120);
+
+    #[builder]
+    fn checked(#[builder(required, validate = "check_small")] n: u32) -> u32 {
+        n * 2
+    }
+
+    fn check_small(n: &u32) -> Result<(), String> {
+        if *n < 10 {
+            Ok(())
+        } else {
+            Err(format!("{n} is too large"))
+        }
+    }
+
+    assert_eq!(checked().with_n(3).try_call().unwrap(), 6);
+    assert_eq!(
+        checked().with_n(30).try_call().unwrap_err().to_string(),
+        "`n` is invalid: 30 is too large"
+    );
+}
+
+#[test]
+fn async_function_builder_success() {
+    #[builder]
+    async fn double(#[builder(required)] n: u32) -> u32 {
+        n * 2
+    }
+
+    let future = double().with_n(21).call();
+    let waker = std::task::Waker::noop();
+    let mut context = std::task::Context::from_waker(waker);
+    let mut future = std::pin::pin!(future);
+    assert_eq!(
+        future.as_mut().poll(&mut context),
+        std::task::Poll::Ready(42)
+    );
+}
+
+mod pool {
+    use typesafe_builder_derive::builder;
+
+    #[derive(Debug, PartialEq)]
+    pub struct Pool {
+        url: String,
+        size: u32,
+        queries: Vec<String>,
+    }
+
+    #[builder]
+    impl Pool {
+        #[builder]
+        pub fn new(
+            #[builder(required, into)] url: String,
+            #[builder(default = "10")] size: u32,
+        ) -> Self {
+            assert!(size > 0, "a pool needs at least one connection");
+            Self {
+                url,
+                size,
+                queries: Vec::new(),
+            }
+        }
+
+        #[builder]
+        pub fn query(
+            &self,
+            #[builder(required, into)] sql: String,
+            #[builder(optional)] limit: Option<u32>,
+        ) -> String {
+            match limit {
+                Some(limit) => format!("{}: {sql} LIMIT {limit}", self.url),
+                None => format!("{}: {sql}", self.url),
+            }
+        }
+
+        #[builder]
+        pub fn record(&mut self, #[builder(required, into)] sql: String) -> &mut Self {
+            self.queries.push(sql);
+            self
+        }
+
+        #[builder]
+        pub async fn resize(self, #[builder(required)] size: u32) -> Self {
+            Self { size, ..self }
+        }
+
+        pub fn size(&self) -> u32 {
+            self.size
+        }
+
+        pub fn queries(&self) -> &[String] {
+            &self.queries
+        }
+    }
+}
+
+#[test]
+fn impl_builder_success() {
+    use pool::Pool;
+
+    // `new` keeps working next to the builder starting with `builder()`.
+    let mut pool = Pool::builder().with_url("postgres://db").call();
+    assert_eq!(pool, Pool::new("postgres://db".to_string(), 10));
+    assert_eq!(pool.size(), 10);
+
+    assert_eq!(
+        pool.query().with_sql("SELECT 1").with_limit(5).call(),
+        "postgres://db: SELECT 1 LIMIT 5"
+    );
+
+    pool.record()
+        .with_sql("SELECT 1")
+        .call()
+        .record()
+        .with_sql("SELECT 2")
+        .call();
+    assert_eq!(pool.queries(), ["SELECT 1", "SELECT 2"]);
+
+    let future = pool.resize().with_size(20).call();
+    let waker = std::task::Waker::noop();
+    let mut context = std::task::Context::from_waker(waker);
+    let mut future = std::pin::pin!(future);
+    match future.as_mut().poll(&mut context) {
+        std::task::Poll::Ready(pool) => assert_eq!(pool.size(), 20),
+        std::task::Poll::Pending => panic!("`resize` does not wait"),
+    }
+}
+
+#[test]
+fn generic_impl_builder_success() {
+    #[derive(Debug, PartialEq)]
+    struct Pair<T> {
+        left: T,
+        right: T,
+    }
+
+    #[builder]
+    impl<T: Clone> Pair<T> {
+        #[builder]
+        fn new(#[builder(required)] left: T, #[builder(optional)] right: Option<T>) -> Self {
+            let right = right.unwrap_or_else(|| left.clone());
+            Self { left, right }
+        }
+
+        #[builder]
+        fn swapped_with<U: Into<T>>(&self, #[builder(required)] left: U) -> Pair<T> {
+            Pair {
+                left: left.into(),
+                right: self.left.clone(),
+            }
+        }
+    }
+
+    let pair = Pair::builder().with_left(1u64).call();
+    assert_eq!(pair, Pair { left: 1, right: 1 });
+    assert_eq!(
+        pair.swapped_with().with_left(2u8).call(),
+        Pair { left: 2, right: 1 }
+    );
+}
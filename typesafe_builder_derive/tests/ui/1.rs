@@ -1,8 +1,5 @@
 use typesafe_builder_derive::Builder;
 
-struct _TypesafeBuilderEmpty;
-struct _TypesafeBuilderFilled;
-
 fn main() {
     #[derive(Builder)]
     struct User {
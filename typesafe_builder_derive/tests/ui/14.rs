@@ -0,0 +1,16 @@
+use typesafe_builder_derive::Builder;
+
+fn main() {
+    #[derive(Builder)]
+    struct User {
+        #[builder(required)]
+        name: String,
+        #[builder(required)]
+        email: String,
+        #[builder(optional)]
+        age: Option<u8>,
+    }
+
+    // compile error because both name and email are required
+    let user = UserBuilder::new().with_age(20).build();
+}
@@ -0,0 +1,16 @@
+use typesafe_builder_derive::Builder;
+
+fn main() {
+    #[derive(Builder)]
+    struct Database {
+        #[builder(optional)]
+        use_ssl: Option<bool>,
+        #[builder(optional)]
+        trusted_network: Option<bool>,
+        #[builder(optional_if = "use_ssl || trusted_network")]
+        warning_message: Option<String>,
+    }
+
+    // compile error because warning_message is required unless use_ssl or trusted_network is set
+    let database = DatabaseBuilder::new().build();
+}
@@ -0,0 +1,18 @@
+use typesafe_builder_derive::Builder;
+
+fn main() {
+    #[derive(Builder)]
+    enum Command {
+        Create {
+            #[builder(required)]
+            name: String,
+        },
+        Delete {
+            #[builder(required)]
+            id: u64,
+        },
+    }
+
+    // compile error because name is required to create
+    let command = CommandBuilder::create().build();
+}
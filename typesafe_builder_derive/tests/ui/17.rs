@@ -0,0 +1,14 @@
+use typesafe_builder_derive::Builder;
+
+fn main() {
+    #[derive(Builder)]
+    struct Range(
+        #[builder(required)]
+        #[builder(setter(name = "start"))]
+        u32,
+        #[builder(required)] u32,
+    );
+
+    // compile error because the second field is required
+    let range = RangeBuilder::new().start(1).build();
+}
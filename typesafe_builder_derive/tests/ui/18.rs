@@ -0,0 +1,17 @@
+use typesafe_builder_derive::Builder;
+
+fn main() {
+    fn non_empty(value: &String) -> Result<(), &'static str> {
+        if value.is_empty() { Err("empty") } else { Ok(()) }
+    }
+
+    #[derive(Builder)]
+    struct User {
+        #[builder(required)]
+        #[builder(validate = "non_empty")]
+        name: String,
+    }
+
+    // compile error because validated builders only provide try_build
+    let user = UserBuilder::new().with_name("Alice".to_string()).build();
+}
@@ -0,0 +1,11 @@
+use typesafe_builder_derive::Builder;
+
+fn main() {
+    // compile error because validate_with needs the error type of build
+    #[derive(Builder)]
+    #[builder(validate_with = "Self::check")]
+    struct Pool {
+        #[builder(required)]
+        max_connections: u32,
+    }
+}
@@ -0,0 +1,18 @@
+use typesafe_builder_derive::Builder;
+
+fn main() {
+    #[derive(Builder)]
+    struct Credentials {
+        #[builder(optional)]
+        #[builder(conflicts_with = "oauth_token")]
+        api_key: Option<String>,
+        #[builder(optional)]
+        oauth_token: Option<String>,
+    }
+
+    // compile error because api_key and oauth_token are both set
+    let credentials = CredentialsBuilder::new()
+        .with_api_key("key".to_string())
+        .with_oauth_token("token".to_string())
+        .build();
+}
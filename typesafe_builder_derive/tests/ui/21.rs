@@ -0,0 +1,20 @@
+use typesafe_builder_derive::Builder;
+
+fn main() {
+    #[derive(Builder)]
+    struct Credentials {
+        #[builder(optional)]
+        api_key: Option<String>,
+        #[builder(optional)]
+        oauth_token: Option<String>,
+        #[builder(optional)]
+        #[builder(conflicts_with = "api_key || oauth_token")]
+        anonymous: Option<bool>,
+    }
+
+    // compile error because anonymous cannot be combined with oauth_token
+    let credentials = CredentialsBuilder::new()
+        .with_oauth_token("token".to_string())
+        .with_anonymous(true)
+        .build();
+}
@@ -0,0 +1,17 @@
+use typesafe_builder_derive::Builder;
+
+fn main() {
+    #[derive(Builder)]
+    struct Login {
+        #[builder(optional)]
+        sso_provider: Option<String>,
+        #[builder(forbidden_if = "sso_provider")]
+        password: Option<String>,
+    }
+
+    // compile error because with_password is unavailable once sso_provider is set
+    let login = LoginBuilder::new()
+        .with_sso_provider("github".to_string())
+        .with_password("secret".to_string())
+        .build();
+}
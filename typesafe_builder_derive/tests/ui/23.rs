@@ -0,0 +1,17 @@
+use typesafe_builder_derive::Builder;
+
+fn main() {
+    #[derive(Builder)]
+    struct Login {
+        #[builder(optional)]
+        sso_provider: Option<String>,
+        #[builder(forbidden_if = "sso_provider")]
+        password: Option<String>,
+    }
+
+    // compile error because password was set before sso_provider
+    let login = LoginBuilder::new()
+        .with_password("secret".to_string())
+        .with_sso_provider("github".to_string())
+        .build();
+}
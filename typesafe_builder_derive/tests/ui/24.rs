@@ -0,0 +1,18 @@
+use typesafe_builder_derive::Builder;
+
+fn main() {
+    #[derive(Builder)]
+    #[builder(group(name = "source", exactly_one = [file, url]))]
+    struct Upload {
+        #[builder(optional)]
+        file: Option<String>,
+        #[builder(optional)]
+        url: Option<String>,
+    }
+
+    // compile error because both sources are set
+    let upload = UploadBuilder::new()
+        .with_file("data.csv".to_string())
+        .with_url("https://example.com".to_string())
+        .build();
+}
@@ -0,0 +1,13 @@
+use typesafe_builder_derive::Builder;
+
+fn main() {
+    // compile error because the group refers to a field that does not exist
+    #[derive(Builder)]
+    #[builder(group(name = "source", at_least_one = [file, uri]))]
+    struct Upload {
+        #[builder(optional)]
+        file: Option<String>,
+        #[builder(optional)]
+        url: Option<String>,
+    }
+}
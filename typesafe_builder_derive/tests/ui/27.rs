@@ -0,0 +1,14 @@
+use typesafe_builder_derive::Builder;
+
+fn main() {
+    // compile error because conditions refer to fields by name only
+    #[derive(Builder)]
+    struct User {
+        #[builder(optional)]
+        email: Option<String>,
+        #[builder(optional)]
+        phone: Option<String>,
+        #[builder(required_if = "self.email")]
+        verified: Option<bool>,
+    }
+}
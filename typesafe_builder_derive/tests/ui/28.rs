@@ -0,0 +1,12 @@
+use typesafe_builder_derive::Builder;
+
+fn main() {
+    // compile error because conditions refer to fields by name only
+    #[derive(Builder)]
+    struct User {
+        #[builder(optional)]
+        email: Option<String>,
+        #[builder(optional_if = "config::email")]
+        verified: Option<bool>,
+    }
+}
@@ -0,0 +1,15 @@
+use typesafe_builder_derive::Builder;
+
+fn main() {
+    // compile error because `email & phone` is not a supported condition
+    #[derive(Builder)]
+    struct User {
+        #[builder(optional)]
+        email: Option<String>,
+        #[builder(optional)]
+        phone: Option<String>,
+        #[builder(optional)]
+        #[builder(conflicts_with = "email & phone")]
+        verified: Option<bool>,
+    }
+}
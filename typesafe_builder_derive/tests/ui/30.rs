@@ -0,0 +1,18 @@
+use typesafe_builder_derive::Builder;
+
+fn main() {
+    // compile error because `tag` is required when exactly one of `image` and `dockerfile` is set
+    #[derive(Builder)]
+    struct Deploy {
+        #[builder(optional)]
+        image: Option<String>,
+        #[builder(optional)]
+        dockerfile: Option<String>,
+        #[builder(required_if = "image ^ dockerfile")]
+        tag: Option<String>,
+    }
+
+    let _deploy = DeployBuilder::new()
+        .with_image("nginx".to_string())
+        .build();
+}
@@ -0,0 +1,18 @@
+use typesafe_builder_derive::Builder;
+
+fn main() {
+    // compile error because `context` is required while `registry -> !image` holds
+    #[derive(Builder)]
+    struct Deploy {
+        #[builder(optional)]
+        image: Option<String>,
+        #[builder(optional)]
+        registry: Option<String>,
+        #[builder(required_if = "registry -> !image")]
+        context: Option<String>,
+    }
+
+    let _deploy = DeployBuilder::new()
+        .with_registry("ghcr.io".to_string())
+        .build();
+}
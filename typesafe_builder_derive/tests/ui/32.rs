@@ -0,0 +1,14 @@
+use typesafe_builder_derive::Builder;
+
+fn main() {
+    // compile error because `at_least` needs a number before its conditions
+    #[derive(Builder)]
+    struct Deploy {
+        #[builder(optional)]
+        image: Option<String>,
+        #[builder(optional)]
+        registry: Option<String>,
+        #[builder(required_if = "at_least(image, registry)")]
+        reviewer: Option<String>,
+    }
+}
@@ -0,0 +1,14 @@
+use typesafe_builder_derive::Builder;
+
+fn main() {
+    // compile error because `count(..)` must be compared with a number
+    #[derive(Builder)]
+    struct Deploy {
+        #[builder(optional)]
+        image: Option<String>,
+        #[builder(optional)]
+        registry: Option<String>,
+        #[builder(required_if = "count(image, registry)")]
+        reviewer: Option<String>,
+    }
+}
@@ -0,0 +1,14 @@
+use typesafe_builder_derive::Builder;
+
+fn main() {
+    // compile error because `at_most` is not a condition function
+    #[derive(Builder)]
+    struct Deploy {
+        #[builder(optional)]
+        image: Option<String>,
+        #[builder(optional)]
+        registry: Option<String>,
+        #[builder(required_if = "at_most(1, image, registry)")]
+        reviewer: Option<String>,
+    }
+}
@@ -0,0 +1,12 @@
+use typesafe_builder_derive::Builder;
+
+fn main() {
+    // compile error because `->` is missing its conclusion
+    #[derive(Builder)]
+    struct Deploy {
+        #[builder(optional)]
+        registry: Option<String>,
+        #[builder(required_if = "registry ->")]
+        context: Option<String>,
+    }
+}
@@ -0,0 +1,20 @@
+use typesafe_builder_derive::Builder;
+
+fn main() {
+    // compile error because `token` is required while the `needs_token` condition holds
+    #[derive(Builder)]
+    #[builder(condition(secure = "use_auth || use_https"))]
+    #[builder(condition(needs_token = "secure && !api_key"))]
+    struct ApiClient {
+        #[builder(default)]
+        use_auth: bool,
+        #[builder(default)]
+        use_https: bool,
+        #[builder(optional)]
+        api_key: Option<String>,
+        #[builder(required_if = "needs_token")]
+        token: Option<String>,
+    }
+
+    let _client = ApiClientBuilder::new().with_use_auth(true).build();
+}
@@ -0,0 +1,15 @@
+use typesafe_builder_derive::Builder;
+
+fn main() {
+    // compile error because the condition `api_key` has the name of a field
+    #[derive(Builder)]
+    #[builder(condition(api_key = "use_auth || use_https"))]
+    struct ApiClient {
+        #[builder(default)]
+        use_auth: bool,
+        #[builder(default)]
+        use_https: bool,
+        #[builder(optional)]
+        api_key: Option<String>,
+    }
+}
@@ -0,0 +1,15 @@
+use typesafe_builder_derive::Builder;
+
+fn main() {
+    // compile error because `secure` and `trusted` are defined in terms of each other
+    #[derive(Builder)]
+    #[builder(condition(secure = "use_https && trusted", trusted = "secure || use_auth"))]
+    struct ApiClient {
+        #[builder(default)]
+        use_auth: bool,
+        #[builder(default)]
+        use_https: bool,
+        #[builder(required_if = "secure")]
+        token: Option<String>,
+    }
+}
@@ -0,0 +1,12 @@
+use typesafe_builder_derive::Builder;
+
+fn main() {
+    // compile error because the condition of `token` refers to `token` itself
+    #[derive(Builder)]
+    struct ApiClient {
+        #[builder(optional)]
+        api_key: Option<String>,
+        #[builder(required_if = "!api_key && !token")]
+        token: Option<String>,
+    }
+}
@@ -0,0 +1,12 @@
+use typesafe_builder_derive::Builder;
+
+fn main() {
+    // compile error because `name` is required, so the condition of `age` always holds
+    #[derive(Builder)]
+    struct User {
+        #[builder(required)]
+        name: String,
+        #[builder(required_if = "name")]
+        age: Option<u32>,
+    }
+}
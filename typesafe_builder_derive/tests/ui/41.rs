@@ -0,0 +1,13 @@
+use typesafe_builder_derive::Builder;
+
+fn main() {
+    // compile error because `api_key && !api_key` never holds
+    #[derive(Builder)]
+    struct ApiClient {
+        #[builder(optional)]
+        api_key: Option<String>,
+        #[builder(optional)]
+        #[builder(conflicts_with = "api_key && !api_key")]
+        token: Option<String>,
+    }
+}
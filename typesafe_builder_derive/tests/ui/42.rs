@@ -0,0 +1,19 @@
+use typesafe_builder_derive::Builder;
+
+fn main() {
+    // compile error because no combination of set fields satisfies every constraint:
+    // `api_key` needs `token`, which requires `refresh`, which `token` conflicts with
+    #[derive(Builder)]
+    struct ApiClient {
+        #[builder(required_if = "!token")]
+        #[builder(conflicts_with = "!token")]
+        api_key: Option<String>,
+        #[builder(optional)]
+        #[builder(conflicts_with = "refresh")]
+        token: Option<String>,
+        #[builder(required_if = "token")]
+        refresh: Option<String>,
+        #[builder(optional)]
+        timeout: Option<u64>,
+    }
+}
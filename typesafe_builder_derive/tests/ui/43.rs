@@ -0,0 +1,14 @@
+use typesafe_builder_derive::Builder;
+
+fn main() {
+    // compile error because `secret` is required while `use_auth` is enabled
+    #[derive(Builder)]
+    struct ApiClient {
+        #[builder(flag)]
+        use_auth: bool,
+        #[builder(required_if = "use_auth")]
+        secret: Option<String>,
+    }
+
+    let _client = ApiClientBuilder::new().enable_use_auth().build();
+}
@@ -0,0 +1,10 @@
+use typesafe_builder_derive::Builder;
+
+fn main() {
+    // compile error because a flag must be a `bool`
+    #[derive(Builder)]
+    struct ApiClient {
+        #[builder(flag)]
+        use_auth: Option<String>,
+    }
+}
@@ -0,0 +1,19 @@
+use typesafe_builder_derive::Builder;
+
+enum Transport {
+    Tcp,
+    Unix,
+}
+
+fn main() {
+    // compile error because `port` is required once `Tcp` is chosen
+    #[derive(Builder)]
+    struct Server {
+        #[builder(selector(Tcp, Unix))]
+        transport: Transport,
+        #[builder(required_if = "transport == Tcp")]
+        port: Option<u16>,
+    }
+
+    let _server = ServerBuilder::new().with_transport_tcp().build();
+}
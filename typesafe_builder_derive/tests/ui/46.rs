@@ -0,0 +1,17 @@
+use typesafe_builder_derive::Builder;
+
+enum Transport {
+    Tcp,
+    Unix,
+}
+
+fn main() {
+    // compile error because `Udp` is not a variant of the selector
+    #[derive(Builder)]
+    struct Server {
+        #[builder(selector(Tcp, Unix))]
+        transport: Transport,
+        #[builder(required_if = "transport == Udp")]
+        port: Option<u16>,
+    }
+}
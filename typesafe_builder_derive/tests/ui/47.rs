@@ -0,0 +1,15 @@
+use typesafe_builder_derive::Builder;
+
+enum Transport {
+    Tcp,
+    Unix,
+}
+
+fn main() {
+    // compile error because the variants of a selector must be listed
+    #[derive(Builder)]
+    struct Server {
+        #[builder(selector)]
+        transport: Transport,
+    }
+}
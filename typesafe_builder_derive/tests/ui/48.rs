@@ -0,0 +1,16 @@
+use typesafe_builder_derive::Builder;
+
+enum Transport {
+    Tcp,
+    Unix,
+    Quic,
+}
+
+fn main() {
+    // compile error because the selector misses the `Quic` variant
+    #[derive(Builder)]
+    struct Server {
+        #[builder(selector(Tcp, Unix))]
+        transport: Transport,
+    }
+}
@@ -0,0 +1,12 @@
+use typesafe_builder_derive::Builder;
+
+fn main() {
+    // compile error because only selectors can be compared with a variant
+    #[derive(Builder)]
+    struct Server {
+        #[builder(optional)]
+        host: Option<String>,
+        #[builder(required_if = "host == Tcp")]
+        port: Option<u16>,
+    }
+}
@@ -0,0 +1,10 @@
+use typesafe_builder_derive::Builder;
+
+fn main() {
+    // compile error because a required field never uses its default
+    #[derive(Builder)]
+    struct Listener {
+        #[builder(required, default = "80")]
+        port: u16,
+    }
+}
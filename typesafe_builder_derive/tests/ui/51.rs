@@ -0,0 +1,14 @@
+use typesafe_builder_derive::Builder;
+
+fn main() {
+    // compile error because `port` is required once `tls` is set
+    #[derive(Builder)]
+    struct Listener {
+        #[builder(optional)]
+        tls: Option<()>,
+        #[builder(default_if = "!tls", default = "80")]
+        port: u16,
+    }
+
+    let _listener = ListenerBuilder::new().with_tls(()).build();
+}
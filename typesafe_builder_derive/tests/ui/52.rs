@@ -0,0 +1,12 @@
+use typesafe_builder_derive::Builder;
+
+fn main() {
+    // compile error because the defaults of `first` and `last` depend on each other
+    #[derive(Builder)]
+    struct Range {
+        #[builder(default = "*last - 1")]
+        first: u32,
+        #[builder(default = "*first + 1")]
+        last: u32,
+    }
+}
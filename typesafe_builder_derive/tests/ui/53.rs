@@ -0,0 +1,10 @@
+use typesafe_builder_derive::Builder;
+
+fn main() {
+    // compile error because variants have no struct to take defaults from
+    #[derive(Builder)]
+    #[builder(default_from_struct)]
+    enum Command {
+        Create { name: String },
+    }
+}
@@ -0,0 +1,12 @@
+use typesafe_builder_derive::Builder;
+
+fn main() {
+    // compile error because `Config` does not implement `Default`
+    #[derive(Builder)]
+    #[builder(default_from_struct)]
+    struct Config {
+        #[builder(required)]
+        name: String,
+        port: u16,
+    }
+}
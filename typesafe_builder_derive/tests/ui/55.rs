@@ -0,0 +1,13 @@
+use typesafe_builder_derive::Builder;
+
+fn main() {
+    // compile error because inferred requirements make `name` required
+    #[derive(Builder)]
+    #[builder(fields(default_requirement = "infer"))]
+    struct User {
+        name: String,
+        email: Option<String>,
+    }
+
+    let _user = UserBuilder::new().with_email("a@example.com".to_string()).build();
+}
@@ -0,0 +1,10 @@
+use typesafe_builder_derive::Builder;
+
+fn main() {
+    // compile error because an optional field must be an `Option`
+    #[derive(Builder)]
+    #[builder(fields(default_requirement = "optional"))]
+    struct User {
+        name: String,
+    }
+}
@@ -0,0 +1,10 @@
+use typesafe_builder_derive::Builder;
+
+fn main() {
+    // compile error because `maybe` is not a requirement
+    #[derive(Builder)]
+    #[builder(fields(default_requirement = "maybe"))]
+    struct User {
+        name: String,
+    }
+}
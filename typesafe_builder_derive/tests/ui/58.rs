@@ -0,0 +1,11 @@
+use typesafe_builder_derive::builder;
+
+#[builder]
+fn connect(#[builder(required)] host: String, #[builder(default = "5432")] port: u16) -> String {
+    format!("{host}:{port}")
+}
+
+fn main() {
+    // compile error because `host` is required
+    connect().with_port(6543).call();
+}
@@ -0,0 +1,9 @@
+use typesafe_builder_derive::builder;
+
+// compile error because the builder cannot store `impl Trait` arguments
+#[builder]
+fn greet(#[builder(required)] name: impl std::fmt::Display) -> String {
+    format!("Hello, {name}")
+}
+
+fn main() {}
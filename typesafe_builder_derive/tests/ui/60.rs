@@ -0,0 +1,15 @@
+use typesafe_builder_derive::builder;
+
+#[builder]
+fn greet(#[builder(required)] name: &str) -> String {
+    format!("Hello, {name}")
+}
+
+fn main() {
+    // compile error because the builder borrows `name` beyond its scope
+    let builder = {
+        let name = String::from("Alice");
+        greet().with_name(&name)
+    };
+    builder.call();
+}
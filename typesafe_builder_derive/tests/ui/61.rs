@@ -0,0 +1,19 @@
+use typesafe_builder_derive::builder;
+
+struct Pool {
+    size: u32,
+}
+
+#[builder]
+impl Pool {
+    #[builder]
+    fn new(#[builder(required)] size: u32) -> Self {
+        Self { size }
+    }
+}
+
+fn main() {
+    // compile error because `size` is required
+    let pool = Pool::builder().call();
+    println!("{}", pool.size);
+}
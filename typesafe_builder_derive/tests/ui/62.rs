@@ -0,0 +1,13 @@
+use typesafe_builder_derive::builder;
+
+struct Pool;
+
+// compile error because the `impl` block is not marked with `#[builder]`
+impl Pool {
+    #[builder]
+    fn query(&self, #[builder(required)] sql: String) -> String {
+        sql
+    }
+}
+
+fn main() {}
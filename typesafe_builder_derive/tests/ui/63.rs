@@ -0,0 +1,14 @@
+#![deny(deprecated)]
+
+use typesafe_builder_derive::builder;
+
+#[deprecated(note = "use `dial` instead")]
+#[builder]
+fn connect(#[builder(required)] host: &str) -> String {
+    format!("tcp://{host}")
+}
+
+fn main() {
+    // compile error because `connect` is deprecated, reported where it is called
+    let _ = connect().with_host("db").call();
+}
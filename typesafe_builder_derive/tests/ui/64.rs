@@ -0,0 +1,15 @@
+use typesafe_builder_derive::builder;
+
+struct Pool {
+    size: u32,
+}
+
+// compile error because the `impl` block is not marked with `#[builder]`
+impl Pool {
+    #[builder]
+    pub fn new(#[builder(required)] size: u32) -> Self {
+        Pool { size }
+    }
+}
+
+fn main() {}
@@ -0,0 +1,23 @@
+use typesafe_builder_derive::Builder;
+
+fn main() {
+    // compile error because the fields of a type implementing `Drop` cannot be moved out of its default value
+    #[derive(Builder)]
+    #[builder(default_from_struct)]
+    struct Connection {
+        #[builder(required)]
+        host: String,
+        port: u16,
+        user: String,
+    }
+
+    impl Default for Connection {
+        fn default() -> Self {
+            Connection { host: "localhost".to_string(), port: 5432, user: "postgres".to_string() }
+        }
+    }
+
+    impl Drop for Connection {
+        fn drop(&mut self) {}
+    }
+}